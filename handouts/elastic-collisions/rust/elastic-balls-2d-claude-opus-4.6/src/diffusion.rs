@@ -0,0 +1,178 @@
+//! Mean-squared displacement of the balls, the self-diffusion coefficient
+//! fitted to it, and Enskog's hard-disk prediction to compare with (k_B = 1).
+
+use std::collections::VecDeque;
+use std::ops::RangeInclusive;
+
+use crate::{Float, Vector, World};
+
+/// MSD(t) averaged over all balls and over many time origins.
+///
+/// Once attached with `World::record_msd`, the world stops every `interval`
+/// of simulated time to sample the unwrapped ball positions. The last
+/// `lags` samples are kept as time origins, so each new sample adds one
+/// displacement per ball for every lag `k · interval` with `k = 1..=lags`.
+///
+/// Balls are identified by their index in `World::balls`: a ball added later
+/// only counts from the first sample it appears in, and `World::clear` drops
+/// the stored origins. With walls the MSD levels off at the box size instead
+/// of growing linearly; use `Boundary::Periodic` to measure diffusion.
+#[derive(Debug, Clone)]
+pub struct MeanSquaredDisplacement<F: Float = f32> {
+    interval: F,
+    /// Simulated time left until the next sample.
+    until_sample: F,
+    /// Unwrapped positions at the latest samples, newest first.
+    origins: VecDeque<Vec<F::Vec2>>,
+    /// Summed squared displacements and their number, per lag; index 0 is a
+    /// lag of one interval.
+    sums: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl<F: Float> MeanSquaredDisplacement<F> {
+    /// Lags of `interval`, `2 · interval`, … up to `lags · interval`. The
+    /// first sample is taken as soon as the world next updates. `interval`
+    /// must be positive.
+    pub fn new(interval: F, lags: usize) -> Self {
+        assert!(interval > F::ZERO, "MSD sampling interval must be positive");
+        let lags = lags.max(1);
+        Self {
+            interval,
+            until_sample: F::ZERO,
+            origins: VecDeque::with_capacity(lags),
+            sums: vec![0.0; lags],
+            counts: vec![0; lags],
+        }
+    }
+
+    pub fn interval(&self) -> F {
+        self.interval
+    }
+
+    /// Longest lag time measured.
+    pub fn max_lag(&self) -> F {
+        self.interval * F::from_f64(self.sums.len() as f64)
+    }
+
+    pub(crate) fn until_sample(&self) -> F {
+        self.until_sample
+    }
+
+    /// Let `dt` of simulated time pass without reaching a sample.
+    pub(crate) fn wait(&mut self, dt: F) {
+        self.until_sample -= dt;
+    }
+
+    /// Add the displacements from every stored origin to `positions`, then
+    /// keep `positions` as the newest origin.
+    pub(crate) fn record(&mut self, positions: Vec<F::Vec2>) {
+        for (lag, origin) in self.origins.iter().enumerate() {
+            for (&now, &then) in positions.iter().zip(origin) {
+                self.sums[lag] += (now - then).length_squared().to_f64();
+            }
+            self.counts[lag] += positions.len().min(origin.len()) as u64;
+        }
+        self.origins.push_front(positions);
+        self.origins.truncate(self.sums.len());
+        self.until_sample = self.interval;
+    }
+
+    /// Drop the time origins, keeping what has been accumulated so far.
+    pub(crate) fn forget_origins(&mut self) {
+        self.origins.clear();
+    }
+
+    /// `(t, MSD(t))` for every lag measured at least once.
+    pub fn values(&self) -> Vec<(F, F)> {
+        self.sums
+            .iter()
+            .zip(&self.counts)
+            .enumerate()
+            .filter(|(_, (_, &count))| count > 0)
+            .map(|(lag, (&sum, &count))| {
+                let t = self.interval * F::from_f64((lag + 1) as f64);
+                (t, F::from_f64(sum / count as f64))
+            })
+            .collect()
+    }
+
+    /// Diffusion coefficient `D` from a least-squares line
+    /// `MSD(t) = 4 D t + c` through the lags in `window`, which should lie in
+    /// the linear, diffusive regime past a few mean free times. `None` with
+    /// fewer than two lags in the window.
+    pub fn diffusion_coefficient(&self, window: RangeInclusive<F>) -> Option<F> {
+        let points: Vec<(f64, f64)> = self
+            .values()
+            .into_iter()
+            .filter(|(t, _)| window.contains(t))
+            .map(|(t, msd)| (t.to_f64(), msd.to_f64()))
+            .collect();
+        if points.len() < 2 {
+            return None;
+        }
+
+        let n = points.len() as f64;
+        let mean_t = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_msd = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|&(t, msd)| (t - mean_t) * (msd - mean_msd))
+            .sum();
+        let variance: f64 = points.iter().map(|&(t, _)| (t - mean_t).powi(2)).sum();
+        // Two dimensions: MSD grows as 2 d D t
+        Some(F::from_f64(covariance / variance / 4.0))
+    }
+}
+
+impl<F: Float> World<F> {
+    /// Start sampling the mean-squared displacement into `msd`, replacing
+    /// any accumulator already attached.
+    pub fn record_msd(&mut self, msd: MeanSquaredDisplacement<F>) {
+        self.msd = Some(msd);
+    }
+
+    pub fn msd(&self) -> Option<&MeanSquaredDisplacement<F>> {
+        self.msd.as_ref()
+    }
+
+    /// Stop sampling and hand back the accumulator.
+    pub fn take_msd(&mut self) -> Option<MeanSquaredDisplacement<F>> {
+        self.msd.take()
+    }
+
+    /// Feed the current unwrapped positions to the attached accumulator.
+    pub(crate) fn sample_msd(&mut self) {
+        let positions = self
+            .balls
+            .iter()
+            .map(|b| self.unwrapped_position(b))
+            .collect();
+        if let Some(msd) = &mut self.msd {
+            msd.record(positions);
+        }
+    }
+
+    /// Enskog's self-diffusion coefficient for hard disks,
+    /// `D_E = 1 / (2 n σ) · √(kT / π m) / g(σ)`, with `n` the number density,
+    /// `σ` the diameter and the contact value `g(σ) = (1 - 7φ/16) / (1 - φ)²`
+    /// consistent with `henderson_pressure`. Meant for equal disks; with
+    /// mixed sizes the mean diameter and mass are used.
+    pub fn enskog_diffusion(&self) -> F {
+        let count = F::from_f64(self.balls.len() as f64);
+        let area = self.area();
+        if count <= F::ZERO || area <= F::ZERO {
+            return F::ZERO;
+        }
+
+        let diameter = self.balls.iter().map(|b| F::TWO * b.radius).sum::<F>() / count;
+        let mass = self.balls.iter().map(|b| b.mass).sum::<F>() / count;
+        // Two degrees of freedom per ball: kT = E / N
+        let kt = self.kinetic_energy() / count;
+        let density = count / area;
+        let phi = self.area_fraction();
+        let contact = (F::ONE - F::from_f64(7.0 / 16.0) * phi) / ((F::ONE - phi) * (F::ONE - phi));
+
+        (kt / (F::PI * mass)).sqrt() / (F::TWO * density * diameter * contact)
+    }
+}
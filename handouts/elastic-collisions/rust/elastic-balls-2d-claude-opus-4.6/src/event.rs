@@ -0,0 +1,302 @@
+use crate::{Ball, Boundary, Float, Side, Vector, World};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, Clone, Copy)]
+enum Partner {
+    Ball(usize),
+    Wall(Side),
+    /// Periodic boxes only: the pair's prediction horizon ran out without a
+    /// collision, so predict the pair again from its new nearest image.
+    Recheck(usize),
+}
+
+/// A predicted collision. `counts` are the collision counters of the
+/// participants (for walls, the wall's own counter) at prediction time; if
+/// either has changed since, some earlier event altered the trajectory and
+/// this one is stale.
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    time: f64,
+    ball: usize,
+    partner: Partner,
+    counts: (u32, u32),
+}
+
+impl PartialEq for Event {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Event {}
+
+impl PartialOrd for Event {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Event {
+    // Reversed so that `BinaryHeap` pops the earliest event first.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.time.total_cmp(&self.time)
+    }
+}
+
+/// Event-driven scheduler: predicts exact ball-ball and ball-wall collision
+/// times and jumps from one collision to the next, so balls never overlap.
+#[derive(Default)]
+pub(crate) struct EventQueue {
+    heap: BinaryHeap<Event>,
+    counts: Vec<u32>,
+    /// Bumped whenever a massive piston on that side changes velocity.
+    wall_counts: [u32; 4],
+    time: f64,
+    primed: bool,
+    boundary: Boundary,
+}
+
+impl EventQueue {
+    /// Force all events to be predicted again on the next advance.
+    pub(crate) fn invalidate(&mut self) {
+        self.primed = false;
+    }
+
+    pub(crate) fn advance<F: Float>(&mut self, world: &mut World<F>, dt: F) {
+        if !self.primed || self.counts.len() != world.balls.len() || self.boundary != world.boundary
+        {
+            self.rebuild(world);
+        }
+
+        let target = self.time + dt.to_f64();
+        while let Some(&event) = self.heap.peek() {
+            if event.time > target {
+                break;
+            }
+            self.heap.pop();
+            if !self.is_valid(&event) {
+                continue;
+            }
+
+            drift(world, F::from_f64(event.time - self.time));
+            self.time = event.time;
+
+            let i = event.ball;
+            match event.partner {
+                Partner::Ball(j) => {
+                    collide(world, i, j);
+                    self.counts[i] += 1;
+                    self.counts[j] += 1;
+                    self.predict(world, i);
+                    self.predict(world, j);
+                }
+                Partner::Wall(side) => {
+                    let ball = &mut world.balls[i];
+                    let before = ball.vel[side.axis()];
+                    match world.pistons.iter_mut().find(|p| p.side == side) {
+                        Some(piston) => {
+                            piston.collide(ball);
+                            if piston.is_massive() {
+                                // The recoil changes every ball's prediction
+                                // for this wall
+                                self.wall_counts[side as usize] += 1;
+                                for k in 0..world.balls.len() {
+                                    if k != i {
+                                        self.predict_side(world, k, side);
+                                    }
+                                }
+                            }
+                        }
+                        None => bounce(ball, side),
+                    }
+                    let ball = &world.balls[i];
+                    world.record_wall_impulse(side, ball.mass * (ball.vel[side.axis()] - before));
+                    self.counts[i] += 1;
+                    self.predict(world, i);
+                }
+                Partner::Recheck(j) => self.predict_pair(world, i, j),
+            }
+        }
+
+        drift(world, F::from_f64(target - self.time));
+        self.time = target;
+    }
+
+    /// Re-predict every ball's collision with the wall on `side` after a
+    /// massive piston there changed velocity between collisions. Predictions
+    /// involving other walls and balls stay valid.
+    pub(crate) fn wall_moved<F: Float>(&mut self, world: &World<F>, side: Side) {
+        if !self.primed || self.counts.len() != world.balls.len() {
+            return;
+        }
+        self.wall_counts[side as usize] += 1;
+        for i in 0..world.balls.len() {
+            self.predict_side(world, i, side);
+        }
+    }
+
+    fn is_valid(&self, event: &Event) -> bool {
+        match event.partner {
+            Partner::Ball(j) | Partner::Recheck(j) => {
+                self.counts[event.ball] == event.counts.0 && self.counts[j] == event.counts.1
+            }
+            Partner::Wall(side) => {
+                self.counts[event.ball] == event.counts.0
+                    && self.wall_counts[side as usize] == event.counts.1
+            }
+        }
+    }
+
+    fn rebuild<F: Float>(&mut self, world: &World<F>) {
+        let len = world.balls.len();
+        self.heap.clear();
+        self.counts = vec![0; len];
+        self.wall_counts = [0; 4];
+        self.time = 0.0;
+        self.primed = true;
+        self.boundary = world.boundary;
+
+        for i in 0..len {
+            self.predict_wall(world, i);
+            for j in (i + 1)..len {
+                self.predict_pair(world, i, j);
+            }
+        }
+    }
+
+    fn predict<F: Float>(&mut self, world: &World<F>, i: usize) {
+        self.predict_wall(world, i);
+        for j in 0..world.balls.len() {
+            if j != i {
+                self.predict_pair(world, i, j);
+            }
+        }
+    }
+
+    fn predict_wall<F: Float>(&mut self, world: &World<F>, i: usize) {
+        if world.boundary == Boundary::Periodic {
+            return;
+        }
+        for side in Side::ALL {
+            self.predict_side(world, i, side);
+        }
+    }
+
+    /// Predict when ball `i` hits the (possibly moving) wall on `side`.
+    fn predict_side<F: Float>(&mut self, world: &World<F>, i: usize, side: Side) {
+        let ball = &world.balls[i];
+        let (wall, wall_vel) = world.wall(side);
+        let axis = side.axis();
+        let inward: F = side.inward();
+
+        let gap = (ball.pos[axis] - wall) * inward - ball.radius;
+        let closing = (wall_vel - ball.vel[axis]) * inward;
+        if closing <= F::ZERO {
+            return;
+        }
+
+        self.heap.push(Event {
+            time: self.time + (gap.max(F::ZERO) / closing).to_f64(),
+            ball: i,
+            partner: Partner::Wall(side),
+            counts: (self.counts[i], self.wall_counts[side as usize]),
+        });
+    }
+
+    fn predict_pair<F: Float>(&mut self, world: &World<F>, i: usize, j: usize) {
+        let (a, b) = (&world.balls[i], &world.balls[j]);
+        let event = match world.boundary {
+            Boundary::Walls => pair_time(b.pos - a.pos, b.vel - a.vel, a.radius + b.radius)
+                .map(|t| (t, Partner::Ball(j))),
+            Boundary::Periodic => {
+                // Until the relative displacement has moved by half a box, a
+                // collision can only happen with one of the 3×3 images
+                // around the nearest one.
+                let dr = world.min_image(b.pos - a.pos);
+                let dv = b.vel - a.vel;
+                let horizon = F::HALF * world.width.min(world.height) / dv.length();
+                let box_size = F::Vec2::new(world.width, world.height);
+                let hit = (-1..=1)
+                    .flat_map(|x| {
+                        (-1..=1).map(move |y| {
+                            F::Vec2::new(F::from_f64(x as f64), F::from_f64(y as f64))
+                        })
+                    })
+                    .filter_map(|image| pair_time(dr + image * box_size, dv, a.radius + b.radius))
+                    .filter(|&t| t <= horizon)
+                    .min_by(F::total_cmp);
+                match hit {
+                    Some(t) => Some((t, Partner::Ball(j))),
+                    None if horizon.is_finite() => Some((horizon, Partner::Recheck(j))),
+                    None => None,
+                }
+            }
+        };
+
+        if let Some((t, partner)) = event {
+            self.heap.push(Event {
+                time: self.time + t.to_f64(),
+                ball: i,
+                partner,
+                counts: (self.counts[i], self.counts[j]),
+            });
+        }
+    }
+}
+
+/// Time until two balls with relative position `dr`, relative velocity `dv`
+/// and contact distance `sigma` touch, if they ever do. Balls that already
+/// overlap while approaching collide immediately.
+fn pair_time<F: Float>(dr: F::Vec2, dv: F::Vec2, sigma: F) -> Option<F> {
+    let approach = dr.dot(dv);
+    if approach >= F::ZERO {
+        return None;
+    }
+
+    let gap = dr.length_squared() - sigma * sigma;
+    if gap <= F::ZERO {
+        return Some(F::ZERO);
+    }
+
+    let vv = dv.length_squared();
+    let disc = approach * approach - vv * gap;
+    if disc < F::ZERO {
+        return None;
+    }
+
+    // Equivalent to -(approach + sqrt(disc)) / vv without the cancellation.
+    Some(gap / (-approach + disc.sqrt()))
+}
+
+fn drift<F: Float>(world: &mut World<F>, dt: F) {
+    for ball in world.balls.iter_mut() {
+        ball.pos += ball.vel * dt;
+    }
+    world.move_pistons(dt);
+    world.wrap_positions();
+}
+
+fn collide<F: Float>(world: &mut World<F>, i: usize, j: usize) {
+    let diff = world.min_image(world.balls[j].pos - world.balls[i].pos);
+    let balls = &mut world.balls;
+    let dist = diff.length();
+    if dist <= F::ZERO {
+        return;
+    }
+    let normal = diff / dist;
+
+    let rel_vel = balls[j].vel - balls[i].vel;
+    let vel_along_normal = rel_vel.dot(normal);
+
+    let m1 = balls[i].mass;
+    let m2 = balls[j].mass;
+    let impulse = F::TWO * vel_along_normal / (m1 + m2);
+    balls[i].vel += normal * (impulse * m2);
+    balls[j].vel -= normal * (impulse * m1);
+}
+
+fn bounce<F: Float>(ball: &mut Ball<F>, side: Side) {
+    let axis = side.axis();
+    ball.vel[axis] = side.inward::<F>() * ball.vel[axis].abs();
+}
@@ -0,0 +1,170 @@
+use macroquad::math::{DVec2, IVec2, Vec2};
+use std::cmp::Ordering;
+use std::fmt::Debug;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Scalar type of the simulation: `f32` (the default, cheapest) or `f64`
+/// for long runs where round-off would otherwise make the energy drift.
+pub trait Float:
+    Copy
+    + Debug
+    + Default
+    + PartialOrd
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + MulAssign
+    + Sum
+    + 'static
+{
+    /// Matching 2D vector: `Vec2` for `f32`, `DVec2` for `f64`.
+    type Vec2: Vector<Self>;
+
+    const ZERO: Self;
+    const HALF: Self;
+    const ONE: Self;
+    const TWO: Self;
+    const PI: Self;
+
+    fn from_f64(x: f64) -> Self;
+    fn to_f64(self) -> f64;
+    /// For drawing.
+    fn to_f32(self) -> f32;
+
+    fn sqrt(self) -> Self;
+    fn abs(self) -> Self;
+    fn round(self) -> Self;
+    fn min(self, other: Self) -> Self;
+    fn max(self, other: Self) -> Self;
+    fn is_finite(self) -> bool;
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+/// Operations the simulation needs from `Float::Vec2`.
+pub trait Vector<F>:
+    Copy
+    + Debug
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Mul<F, Output = Self>
+    + Div<Output = Self>
+    + Div<F, Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Sum
+    + Index<usize, Output = F>
+    + IndexMut<usize>
+{
+    const ZERO: Self;
+
+    fn new(x: F, y: F) -> Self;
+    fn dot(self, other: Self) -> F;
+    fn length_squared(self) -> F;
+    fn length(self) -> F;
+    fn floor(self) -> Self;
+    fn as_ivec2(self) -> IVec2;
+    fn from_ivec2(v: IVec2) -> Self;
+    /// For drawing.
+    fn as_vec2(self) -> Vec2;
+}
+
+macro_rules! impl_float {
+    ($float:ident, $vec:ident, $to_vec2:expr) => {
+        impl Float for $float {
+            type Vec2 = $vec;
+
+            const ZERO: Self = 0.0;
+            const HALF: Self = 0.5;
+            const ONE: Self = 1.0;
+            const TWO: Self = 2.0;
+            const PI: Self = std::$float::consts::PI;
+
+            fn from_f64(x: f64) -> Self {
+                x as $float
+            }
+
+            fn to_f64(self) -> f64 {
+                self as f64
+            }
+
+            fn to_f32(self) -> f32 {
+                self as f32
+            }
+
+            fn sqrt(self) -> Self {
+                $float::sqrt(self)
+            }
+
+            fn abs(self) -> Self {
+                $float::abs(self)
+            }
+
+            fn round(self) -> Self {
+                $float::round(self)
+            }
+
+            fn min(self, other: Self) -> Self {
+                $float::min(self, other)
+            }
+
+            fn max(self, other: Self) -> Self {
+                $float::max(self, other)
+            }
+
+            fn is_finite(self) -> bool {
+                $float::is_finite(self)
+            }
+
+            fn total_cmp(&self, other: &Self) -> Ordering {
+                $float::total_cmp(self, other)
+            }
+        }
+
+        impl Vector<$float> for $vec {
+            const ZERO: Self = $vec::ZERO;
+
+            fn new(x: $float, y: $float) -> Self {
+                $vec::new(x, y)
+            }
+
+            fn dot(self, other: Self) -> $float {
+                $vec::dot(self, other)
+            }
+
+            fn length_squared(self) -> $float {
+                $vec::length_squared(self)
+            }
+
+            fn length(self) -> $float {
+                $vec::length(self)
+            }
+
+            fn floor(self) -> Self {
+                $vec::floor(self)
+            }
+
+            fn as_ivec2(self) -> IVec2 {
+                $vec::as_ivec2(&self)
+            }
+
+            fn from_ivec2(v: IVec2) -> Self {
+                $vec::new(v.x as $float, v.y as $float)
+            }
+
+            fn as_vec2(self) -> Vec2 {
+                $to_vec2(self)
+            }
+        }
+    };
+}
+
+impl_float!(f32, Vec2, |v: Vec2| v);
+impl_float!(f64, DVec2, |v: DVec2| v.as_vec2());
@@ -0,0 +1,64 @@
+use crate::{Ball, Float, Vector};
+
+/// Force on a ball computed from its state.
+type CustomForce<F> = dyn Fn(&Ball<F>) -> <F as Float>::Vec2;
+
+/// An external force acting on every ball.
+pub enum ForceField<F: Float = f32> {
+    /// Uniform gravitational acceleration `g`: F = m g.
+    Gravity(F::Vec2),
+    /// Harmonic trap around `center`: U = ½ k |r - c|².
+    Harmonic { center: F::Vec2, stiffness: F },
+    /// Softened 1/r potential around `center`: U = -k / √(|r - c|² + ε²).
+    /// Attractive for positive `strength`.
+    InverseDistance {
+        center: F::Vec2,
+        strength: F,
+        softening: F,
+    },
+    /// Arbitrary force computed from the ball's state.
+    Custom(Box<CustomForce<F>>),
+}
+
+impl<F: Float> ForceField<F> {
+    pub fn custom(force: impl Fn(&Ball<F>) -> F::Vec2 + 'static) -> Self {
+        ForceField::Custom(Box::new(force))
+    }
+
+    pub fn force(&self, ball: &Ball<F>) -> F::Vec2 {
+        match self {
+            ForceField::Gravity(g) => *g * ball.mass,
+            ForceField::Harmonic { center, stiffness } => -(ball.pos - *center) * *stiffness,
+            ForceField::InverseDistance {
+                center,
+                strength,
+                softening,
+            } => {
+                let d = ball.pos - *center;
+                let r2 = d.length_squared() + *softening * *softening;
+                -d * (*strength / (r2 * r2.sqrt()))
+            }
+            ForceField::Custom(force) => force(ball),
+        }
+    }
+
+    /// Potential energy of `ball` in this field, or `None` for custom forces,
+    /// which need not be conservative.
+    pub fn potential_energy(&self, ball: &Ball<F>) -> Option<F> {
+        match self {
+            ForceField::Gravity(g) => Some(-ball.mass * g.dot(ball.pos)),
+            ForceField::Harmonic { center, stiffness } => {
+                Some(F::HALF * *stiffness * (ball.pos - *center).length_squared())
+            }
+            ForceField::InverseDistance {
+                center,
+                strength,
+                softening,
+            } => {
+                let r2 = (ball.pos - *center).length_squared() + *softening * *softening;
+                Some(-*strength / r2.sqrt())
+            }
+            ForceField::Custom(_) => None,
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use crate::{Ball, Float, Vector, World};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::ops::Range;
+
+/// Random initial conditions drawn from a seeded generator, so that a run can
+/// be replayed exactly from its seed.
+pub struct InitialConditions {
+    seed: u64,
+    rng: StdRng,
+    /// Range the ball radii are drawn from.
+    pub radius: Range<f64>,
+    /// Each velocity component is drawn from `-max_velocity..max_velocity`.
+    pub max_velocity: f64,
+}
+
+impl InitialConditions {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+            radius: 10.0..40.0,
+            max_velocity: 300.0,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// Next ball from the sequence, placed uniformly inside a
+    /// `width × height` box.
+    pub fn ball<F: Float>(&mut self, width: F, height: F) -> Ball<F> {
+        random_ball(
+            &mut self.rng,
+            self.radius.clone(),
+            self.max_velocity,
+            width.to_f64(),
+            height.to_f64(),
+        )
+    }
+
+    /// Replace the balls in `world` by `count` random ones, restarting the
+    /// sequence from the seed so that the same seed always gives the same
+    /// world.
+    pub fn populate<F: Float>(&mut self, world: &mut World<F>, count: usize) {
+        self.rng = StdRng::seed_from_u64(self.seed);
+        world.clear();
+        for _ in 0..count {
+            let ball = self.ball(world.width, world.height);
+            world.add_ball(ball);
+        }
+    }
+}
+
+/// Seed requested as `--seed N` among the command-line `args`, or failing
+/// that in `env` (the value of `ELASTIC_SEED`). `Ok(None)` if neither gives
+/// one; an error message if `--seed` has no value or a value is not a
+/// non-negative integer.
+pub fn requested_seed(args: &[String], env: Option<&str>) -> Result<Option<u64>, String> {
+    let parse = |value: &str, source: &str| {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{source} must be a non-negative integer, got `{value}`"))
+    };
+    match args.iter().position(|arg| arg == "--seed") {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => parse(value, "--seed"),
+            None => Err("--seed needs a value".to_string()),
+        },
+        None => env.map_or(Ok(None), |value| parse(value, "ELASTIC_SEED")),
+    }
+}
+
+/// A ball with random size, position, velocity and colour drawn from `rng`.
+pub fn random_ball<F: Float, R: Rng + ?Sized>(
+    rng: &mut R,
+    radius: Range<f64>,
+    max_velocity: f64,
+    width: f64,
+    height: f64,
+) -> Ball<F> {
+    let radius = rng.gen_range(radius);
+    let pos = F::Vec2::new(
+        F::from_f64(rng.gen_range(radius..width - radius)),
+        F::from_f64(rng.gen_range(radius..height - radius)),
+    );
+    let vel = F::Vec2::new(
+        F::from_f64(rng.gen_range(-max_velocity..max_velocity)),
+        F::from_f64(rng.gen_range(-max_velocity..max_velocity)),
+    );
+    let color = [
+        rng.gen_range(0.3..1.0),
+        rng.gen_range(0.3..1.0),
+        rng.gen_range(0.3..1.0),
+        1.0,
+    ];
+    Ball::new(pos, vel, F::from_f64(radius), color)
+}
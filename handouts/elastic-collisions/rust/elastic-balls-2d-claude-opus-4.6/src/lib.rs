@@ -1,120 +1,416 @@
-use macroquad::math::Vec2;
-use std::f32::consts::PI;
-
-pub struct Ball {
-    pub pos: Vec2,
-    pub vel: Vec2,
-    pub radius: f32,
-    pub mass: f32,
+use macroquad::math::IVec2;
+
+mod diffusion;
+mod event;
+mod float;
+mod force;
+mod init;
+mod piston;
+mod pressure;
+
+pub use diffusion::MeanSquaredDisplacement;
+use event::EventQueue;
+pub use float::{Float, Vector};
+pub use force::ForceField;
+pub use init::{random_ball, requested_seed, InitialConditions};
+pub use piston::{Piston, PistonDrive, Side};
+
+/// A ball whose state is stored in `F` precision (`f32` or `f64`).
+pub struct Ball<F: Float = f32> {
+    pub pos: F::Vec2,
+    pub vel: F::Vec2,
+    pub radius: F,
+    pub mass: F,
     pub color: [f32; 4],
+    /// Net number of times the ball has wrapped around each axis of a
+    /// periodic box; see `World::unwrapped_position`.
+    pub image: IVec2,
 }
 
-impl Ball {
-    pub fn new(pos: Vec2, vel: Vec2, radius: f32, color: [f32; 4]) -> Self {
+impl<F: Float> Ball<F> {
+    pub fn new(pos: F::Vec2, vel: F::Vec2, radius: F, color: [f32; 4]) -> Self {
         Self {
             pos,
             vel,
             radius,
-            mass: PI * radius * radius,
+            mass: F::PI * radius * radius,
             color,
+            image: IVec2::ZERO,
         }
     }
 }
 
-pub struct World {
-    pub balls: Vec<Ball>,
-    pub width: f32,
-    pub height: f32,
+/// How `World::update` advances the simulation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Fixed sub-steps with overlap detection and position correction.
+    TimeStepped,
+    /// Exact collision times; balls jump from one collision to the next.
+    /// Force fields make the balls accelerate between collisions, so `update`
+    /// then falls back to sub-steps of 1/120 s and predicts every collision
+    /// again, at O(N²) cost, on each of them. Massive pistons on their own
+    /// only re-predict the collisions with their wall.
+    EventDriven,
+}
+
+/// What happens at the edges of the `width × height` box.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Boundary {
+    /// Balls bounce off four hard walls.
+    #[default]
+    Walls,
+    /// Balls leaving one side re-enter on the opposite side, and distances are
+    /// measured with the minimum-image convention. Both sides of the box must
+    /// be longer than twice the largest ball diameter.
+    Periodic,
+}
+
+/// The simulation, in `f32` by default; use `World<f64>` for long runs.
+pub struct World<F: Float = f32> {
+    pub balls: Vec<Ball<F>>,
+    pub width: F,
+    pub height: F,
     pub paused: bool,
-    pub speed_multiplier: f32,
+    pub speed_multiplier: F,
+    pub mode: Mode,
+    pub boundary: Boundary,
+    pub forces: Vec<ForceField<F>>,
+    /// Movable walls, at most one per side; see `add_piston`. They stand
+    /// still while the boundary is periodic.
+    pub pistons: Vec<Piston<F>>,
+    /// Momentum each wall has received from the balls since the last
+    /// `reset_pressure`, along its outward normal, indexed by `Side as usize`.
+    wall_impulse: [F; 4],
+    /// Simulated time since the last `reset_pressure`.
+    sample_time: F,
+    /// Mean-squared displacement accumulator; see `record_msd`.
+    msd: Option<MeanSquaredDisplacement<F>>,
+    events: EventQueue,
 }
 
-impl World {
-    pub fn new(width: f32, height: f32) -> Self {
+impl<F: Float> World<F> {
+    pub fn new(width: F, height: F) -> Self {
         Self {
             balls: Vec::new(),
             width,
             height,
             paused: false,
-            speed_multiplier: 1.0,
+            speed_multiplier: F::ONE,
+            mode: Mode::TimeStepped,
+            boundary: Boundary::Walls,
+            forces: Vec::new(),
+            pistons: Vec::new(),
+            wall_impulse: [F::ZERO; 4],
+            sample_time: F::ZERO,
+            msd: None,
+            events: EventQueue::default(),
         }
     }
 
-    pub fn add_ball(&mut self, ball: Ball) {
+    pub fn add_ball(&mut self, ball: Ball<F>) {
         self.balls.push(ball);
+        self.events.invalidate();
     }
 
     pub fn clear(&mut self) {
         self.balls.clear();
+        self.events.invalidate();
+        if let Some(msd) = &mut self.msd {
+            msd.forget_origins();
+        }
     }
 
     pub fn ball_count(&self) -> usize {
         self.balls.len()
     }
 
-    pub fn resize(&mut self, width: f32, height: f32) {
+    pub fn resize(&mut self, width: F, height: F) {
+        if width != self.width || height != self.height {
+            self.events.invalidate();
+            // Unwrapped positions count wraps in units of the old size
+            if let Some(msd) = &mut self.msd {
+                msd.forget_origins();
+            }
+        }
         self.width = width;
         self.height = height;
     }
 
-    pub fn update(&mut self, dt: f32) {
+    pub fn add_force(&mut self, field: ForceField<F>) {
+        self.forces.push(field);
+    }
+
+    pub fn clear_forces(&mut self) {
+        self.forces.clear();
+    }
+
+    /// Replace one side of the box with a piston, removing any piston
+    /// already on that side.
+    pub fn add_piston(&mut self, piston: Piston<F>) {
+        self.pistons.retain(|p| p.side != piston.side);
+        self.pistons.push(piston);
+        self.events.invalidate();
+    }
+
+    pub fn clear_pistons(&mut self) {
+        self.pistons.clear();
+        self.events.invalidate();
+    }
+
+    pub fn piston(&self, side: Side) -> Option<&Piston<F>> {
+        self.pistons.iter().find(|p| p.side == side)
+    }
+
+    /// Position and velocity of the wall on `side`: a piston if there is one,
+    /// otherwise the fixed edge of the `width × height` box.
+    pub fn wall(&self, side: Side) -> (F, F) {
+        if let Some(piston) = self.piston(side) {
+            return (piston.position, piston.velocity);
+        }
+        match side {
+            Side::Left | Side::Top => (F::ZERO, F::ZERO),
+            Side::Right => (self.width, F::ZERO),
+            Side::Bottom => (self.height, F::ZERO),
+        }
+    }
+
+    /// Area enclosed by the walls, including pistons.
+    pub fn area(&self) -> F {
+        let w = self.wall(Side::Right).0 - self.wall(Side::Left).0;
+        let h = self.wall(Side::Bottom).0 - self.wall(Side::Top).0;
+        w.max(F::ZERO) * h.max(F::ZERO)
+    }
+
+    pub fn kinetic_energy(&self) -> F {
+        self.balls
+            .iter()
+            .map(|b| F::HALF * b.mass * b.vel.length_squared())
+            .sum()
+    }
+
+    /// Total potential energy in the registered force fields. Custom forces
+    /// contribute nothing.
+    pub fn potential_energy(&self) -> F {
+        self.balls
+            .iter()
+            .flat_map(|ball| self.forces.iter().filter_map(|f| f.potential_energy(ball)))
+            .sum()
+    }
+
+    /// Shortest periodic image of a displacement (unchanged with walls).
+    pub fn min_image(&self, d: F::Vec2) -> F::Vec2 {
+        match self.boundary {
+            Boundary::Walls => d,
+            Boundary::Periodic => F::Vec2::new(
+                d[0] - self.width * (d[0] / self.width).round(),
+                d[1] - self.height * (d[1] / self.height).round(),
+            ),
+        }
+    }
+
+    /// Position of `ball` as if the periodic box never wrapped it, for
+    /// displacement and transport measurements.
+    pub fn unwrapped_position(&self, ball: &Ball<F>) -> F::Vec2 {
+        ball.pos + F::Vec2::from_ivec2(ball.image) * F::Vec2::new(self.width, self.height)
+    }
+
+    /// Move balls that left a periodic box back inside, counting the wraps.
+    pub(crate) fn wrap_positions(&mut self) {
+        if self.boundary != Boundary::Periodic {
+            return;
+        }
+
+        let size = F::Vec2::new(self.width, self.height);
+        for ball in self.balls.iter_mut() {
+            let shift = (ball.pos / size).floor();
+            ball.pos -= shift * size;
+            ball.image += shift.as_ivec2();
+
+            // Rounding can land a ball exactly on the far edge
+            for axis in 0..2 {
+                if ball.pos[axis] >= size[axis] {
+                    ball.pos[axis] -= size[axis];
+                    ball.image[axis] += 1;
+                }
+            }
+        }
+    }
+
+    /// Discard predicted collisions after editing `balls` or `pistons`
+    /// directly.
+    pub fn invalidate_events(&mut self) {
+        self.events.invalidate();
+    }
+
+    pub fn update(&mut self, dt: F) {
         if self.paused {
             return;
         }
 
-        let total_dt = dt * self.speed_multiplier;
-        let max_sub_dt = 1.0 / 120.0;
+        // Stop at every MSD sample, so that its lags are exact multiples of
+        // the sampling interval
+        let mut remaining = dt * self.speed_multiplier;
+        while let Some(wait) = self.msd.as_ref().map(|msd| msd.until_sample()) {
+            if wait > remaining {
+                break;
+            }
+            self.advance(wait);
+            remaining -= wait;
+            self.sample_msd();
+        }
+        if let Some(msd) = &mut self.msd {
+            msd.wait(remaining);
+        }
+        self.advance(remaining);
+    }
+
+    /// Advance the simulation by `total_dt` of simulated time.
+    fn advance(&mut self, total_dt: F) {
+        self.sample_time += total_dt;
+
+        // Free flight: jump straight from collision to collision
+        if self.mode == Mode::EventDriven && !self.has_forces() {
+            self.advance_events(total_dt);
+            return;
+        }
+
+        if self.mode == Mode::TimeStepped {
+            // Switching back to event-driven mode must re-predict from scratch
+            self.events.invalidate();
+        }
+
+        let max_sub_dt = F::from_f64(1.0 / 120.0);
         let mut remaining = total_dt;
 
-        while remaining > 0.0 {
+        while remaining > F::ZERO {
             let sub_dt = remaining.min(max_sub_dt);
             remaining -= sub_dt;
-            self.step(sub_dt);
+
+            // Kick-drift-kick (velocity Verlet), collisions handled in the drift
+            self.kick(F::HALF * sub_dt);
+            match self.mode {
+                Mode::TimeStepped => self.step(sub_dt),
+                Mode::EventDriven => self.advance_events(sub_dt),
+            }
+            self.kick(F::HALF * sub_dt);
         }
     }
 
-    fn step(&mut self, dt: f32) {
-        // Move balls
-        for ball in self.balls.iter_mut() {
-            ball.pos += ball.vel * dt;
+    fn advance_events(&mut self, dt: F) {
+        let mut events = std::mem::take(&mut self.events);
+        events.advance(self, dt);
+        self.events = events;
+    }
+
+    /// Whether anything accelerates between collisions, so that `update`
+    /// has to sub-step.
+    fn has_forces(&self) -> bool {
+        !self.forces.is_empty()
+            || (self.pistons_active() && self.pistons.iter().any(Piston::is_massive))
+    }
+
+    /// Pistons only move with walls: a periodic box has nothing to push
+    /// back on them, so they stay put until the walls return.
+    fn pistons_active(&self) -> bool {
+        self.boundary == Boundary::Walls
+    }
+
+    fn kick(&mut self, dt: F) {
+        if !self.has_forces() {
+            return;
         }
 
-        // Wall collisions
         for ball in self.balls.iter_mut() {
-            let r = ball.radius;
+            let force: F::Vec2 = self.forces.iter().map(|f| f.force(ball)).sum();
+            ball.vel += force * (dt / ball.mass);
+        }
+        if self.pistons_active() {
+            for piston in self.pistons.iter_mut() {
+                piston.kick(dt);
+            }
+        }
 
-            if ball.pos.x - r < 0.0 {
-                ball.vel.x = ball.vel.x.abs();
-                ball.pos.x = r;
-            } else if ball.pos.x + r > self.width {
-                ball.vel.x = -ball.vel.x.abs();
-                ball.pos.x = self.width - r;
+        if self.forces.is_empty() {
+            // Only the massive pistons accelerated: just their walls need
+            // predicting again
+            let mut events = std::mem::take(&mut self.events);
+            for side in Side::ALL {
+                if self.piston(side).is_some_and(Piston::is_massive) {
+                    events.wall_moved(self, side);
+                }
             }
+            self.events = events;
+        } else {
+            // Every ball accelerated, so every predicted collision is stale
+            self.events.invalidate();
+        }
+    }
+
+    /// Advance pistons by `dt` at their current velocities.
+    pub(crate) fn move_pistons(&mut self, dt: F) {
+        if !self.pistons_active() {
+            return;
+        }
+        for piston in self.pistons.iter_mut() {
+            piston.position += piston.velocity * dt;
+        }
+    }
 
-            if ball.pos.y - r < 0.0 {
-                ball.vel.y = ball.vel.y.abs();
-                ball.pos.y = r;
-            } else if ball.pos.y + r > self.height {
-                ball.vel.y = -ball.vel.y.abs();
-                ball.pos.y = self.height - r;
+    fn bounce_off_walls(&mut self) {
+        for side in Side::ALL {
+            let (wall, _) = self.wall(side);
+            let axis = side.axis();
+            let inward = side.inward();
+            let mut piston = self.pistons.iter_mut().find(|p| p.side == side);
+            let mut impulse = F::ZERO;
+
+            for ball in self.balls.iter_mut() {
+                let r = ball.radius;
+                if (ball.pos[axis] - wall) * inward >= r {
+                    continue;
+                }
+
+                ball.pos[axis] = wall + inward * r;
+                let before = ball.vel[axis];
+                match piston.as_deref_mut() {
+                    // Moving walls: elastic collision in the wall's frame
+                    Some(piston) => piston.collide(ball),
+                    None => ball.vel[axis] = inward * ball.vel[axis].abs(),
+                }
+                impulse += ball.mass * (ball.vel[axis] - before);
             }
+            self.record_wall_impulse(side, impulse);
+        }
+    }
+
+    fn step(&mut self, dt: F) {
+        // Move balls and pistons
+        for ball in self.balls.iter_mut() {
+            ball.pos += ball.vel * dt;
+        }
+        self.move_pistons(dt);
+
+        // Wall collisions or periodic wrap-around
+        match self.boundary {
+            Boundary::Walls => self.bounce_off_walls(),
+            Boundary::Periodic => self.wrap_positions(),
         }
 
         // Ball-ball collisions
         let len = self.balls.len();
         for i in 0..len {
             for j in (i + 1)..len {
-                let diff = self.balls[j].pos - self.balls[i].pos;
+                let diff = self.min_image(self.balls[j].pos - self.balls[i].pos);
                 let dist = diff.length();
                 let min_dist = self.balls[i].radius + self.balls[j].radius;
 
-                if dist < min_dist && dist > 0.0 {
+                if dist < min_dist && dist > F::ZERO {
                     let normal = diff / dist;
 
                     // Check if balls are already separating
                     let rel_vel = self.balls[j].vel - self.balls[i].vel;
                     let vel_along_normal = rel_vel.dot(normal);
-                    if vel_along_normal > 0.0 {
+                    if vel_along_normal > F::ZERO {
                         continue;
                     }
 
@@ -127,11 +423,580 @@ impl World {
                     self.balls[j].pos += normal * (overlap * m1 / total_mass);
 
                     // Elastic collision impulse (restitution = 1.0)
-                    let impulse = 2.0 * vel_along_normal / total_mass;
+                    let impulse = F::TWO * vel_along_normal / total_mass;
                     self.balls[i].vel += normal * (impulse * m2);
                     self.balls[j].vel -= normal * (impulse * m1);
                 }
             }
         }
+
+        // Position corrections may push balls across a periodic seam
+        self.wrap_positions();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use macroquad::math::DVec2;
+
+    /// Eight balls of different sizes in a 400 × 300 box with walls.
+    fn gas<F: Float>() -> World<F> {
+        let mut world = World::new(F::from_f64(400.0), F::from_f64(300.0));
+        for k in 0..8 {
+            let angle = 0.9 * k as f64 + 0.3;
+            let pos = F::Vec2::new(
+                F::from_f64(60.0 + 90.0 * (k % 4) as f64),
+                F::from_f64(80.0 + 140.0 * (k / 4) as f64),
+            );
+            let vel = F::Vec2::new(
+                F::from_f64(250.0 * angle.cos()),
+                F::from_f64(250.0 * angle.sin()),
+            );
+            let radius = F::from_f64(12.0 + 3.0 * k as f64);
+            world.add_ball(Ball::new(pos, vel, radius, [1.0; 4]));
+        }
+        world
+    }
+
+    /// Relative change in kinetic energy after `steps` frames of 1/120 s.
+    fn energy_drift<F: Float>(steps: usize) -> f64 {
+        let mut world = gas::<F>();
+        let initial = world.kinetic_energy().to_f64();
+        let dt = F::from_f64(1.0 / 120.0);
+        for _ in 0..steps {
+            world.update(dt);
+        }
+        ((world.kinetic_energy().to_f64() - initial) / initial).abs()
+    }
+
+    #[test]
+    fn double_precision_reduces_energy_drift() {
+        let single = energy_drift::<f32>(1_000_000);
+        let double = energy_drift::<f64>(1_000_000);
+        assert!(double < 1e-9, "f64 drift {double:e}");
+        assert!(
+            double * 1e3 < single,
+            "f32 drift {single:e}, f64 drift {double:e}"
+        );
+    }
+
+    #[test]
+    fn event_driven_gas_never_overlaps_and_conserves_energy_and_momentum() {
+        for boundary in [Boundary::Walls, Boundary::Periodic] {
+            let mut world = gas::<f64>();
+            world.mode = Mode::EventDriven;
+            world.boundary = boundary;
+            let energy = world.kinetic_energy();
+            let momentum =
+                |world: &World<f64>| -> DVec2 { world.balls.iter().map(|b| b.vel * b.mass).sum() };
+            let initial_momentum = momentum(&world);
+
+            for _ in 0..2000 {
+                world.update(1.0 / 60.0);
+                for (i, a) in world.balls.iter().enumerate() {
+                    for b in &world.balls[i + 1..] {
+                        let dist = world.min_image(b.pos - a.pos).length();
+                        assert!(dist > a.radius + b.radius - 1e-9, "{boundary:?}: {dist}");
+                    }
+                }
+            }
+
+            assert!((world.kinetic_energy() / energy - 1.0).abs() < 1e-12);
+            // Walls take up momentum; a periodic box has nothing to push on
+            if boundary == Boundary::Periodic {
+                let drift = (momentum(&world) - initial_momentum).length();
+                assert!(drift < 1e-9 * initial_momentum.length().max(1.0));
+            }
+        }
+    }
+
+    #[test]
+    fn event_driven_matches_time_stepped_for_simple_collisions() {
+        let run = |mode: Mode, balls: &[(DVec2, DVec2, f64)]| {
+            let mut world = World::<f64>::new(1000.0, 600.0);
+            world.mode = mode;
+            for &(pos, vel, radius) in balls {
+                world.add_ball(Ball::new(pos, vel, radius, [1.0; 4]));
+            }
+            for _ in 0..4 {
+                world.update(0.5);
+            }
+            world
+        };
+
+        // Head-on, masses 1 : 4: touching after 0.7 s, then moving at -110
+        // and -10
+        let head_on = [
+            (DVec2::new(400.0, 300.0), DVec2::new(50.0, 0.0), 10.0),
+            (DVec2::new(500.0, 300.0), DVec2::new(-50.0, 0.0), 20.0),
+        ];
+        // Oblique bounce off the right wall after 0.75 s
+        let wall = [(DVec2::new(900.0, 300.0), DVec2::new(120.0, 30.0), 10.0)];
+        let expected = [
+            vec![(DVec2::new(292.0, 300.0), DVec2::new(-110.0, 0.0))],
+            vec![(DVec2::new(840.0, 360.0), DVec2::new(-120.0, 30.0))],
+        ];
+
+        for (balls, expected) in [&head_on[..], &wall[..]].into_iter().zip(expected) {
+            let exact = run(Mode::EventDriven, balls);
+            let stepped = run(Mode::TimeStepped, balls);
+            for ((pos, vel), ball) in expected.iter().zip(&exact.balls) {
+                assert!((ball.pos - *pos).length() < 1e-9, "{:?}", ball.pos);
+                assert!((ball.vel - *vel).length() < 1e-9, "{:?}", ball.vel);
+            }
+            // A fixed step resolves contacts up to one sub-step late
+            for (a, b) in exact.balls.iter().zip(&stepped.balls) {
+                assert!((a.pos - b.pos).length() < 2.0, "{:?} vs {:?}", a.pos, b.pos);
+                assert!(
+                    (a.vel - b.vel).length() < 1e-9,
+                    "{:?} vs {:?}",
+                    a.vel,
+                    b.vel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn gravity_settles_gas_into_barometric_profile() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // 100 small disks spread evenly through a tall box
+        let (width, height, g) = (100.0, 300.0, 6.0);
+        let mut rng = StdRng::seed_from_u64(5);
+        let mut world = World::<f64>::new(width, height);
+        world.add_force(ForceField::Gravity(DVec2::new(0.0, g)));
+        for k in 0..100 {
+            let pos = DVec2::new(5.0 + 10.0 * (k % 10) as f64, 15.0 + 30.0 * (k / 10) as f64);
+            let vel = DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * 40.0;
+            world.add_ball(Ball::new(pos, vel, 1.0, [1.0; 4]));
+        }
+
+        for _ in 0..60 {
+            world.update(1.0);
+        }
+        let bins = 10;
+        let mut counts = vec![0.0_f64; bins];
+        let mut kt = 0.0;
+        let samples = 2000;
+        for _ in 0..samples {
+            world.update(0.1);
+            for ball in &world.balls {
+                let bin = (ball.pos.y / height * bins as f64) as usize;
+                counts[bin.min(bins - 1)] += 1.0;
+            }
+            // Two degrees of freedom per ball: kT = E / N
+            kt += world.kinetic_energy() / 100.0 / samples as f64;
+        }
+
+        // n(y) ∝ exp(m g y / kT): fit log n over the bins clear of the walls
+        let mass = world.balls[0].mass;
+        let expected = mass * g / kt;
+        let points: Vec<(f64, f64)> = (1..bins - 1)
+            .map(|k| ((k as f64 + 0.5) * height / bins as f64, counts[k].ln()))
+            .collect();
+        let n = points.len() as f64;
+        let mean_y = points.iter().map(|p| p.0).sum::<f64>() / n;
+        let mean_ln = points.iter().map(|p| p.1).sum::<f64>() / n;
+        let covariance: f64 = points
+            .iter()
+            .map(|&(y, l)| (y - mean_y) * (l - mean_ln))
+            .sum();
+        let variance: f64 = points.iter().map(|&(y, _)| (y - mean_y).powi(2)).sum();
+        // The excluded area of the crowded bottom bins flattens it slightly
+        let slope = covariance / variance;
+        assert!(
+            (slope / expected - 1.0).abs() < 0.15,
+            "slope {slope}, m g / kT = {expected}, counts {counts:?}"
+        );
+    }
+
+    #[test]
+    fn energy_with_potential_is_conserved_without_collisions() {
+        // A lone ball under gravity, in a harmonic trap and around a
+        // softened attractor, never reaching the walls
+        for k in 0..4 {
+            for mode in [Mode::TimeStepped, Mode::EventDriven] {
+                let mut world = World::<f64>::new(2000.0, 2000.0);
+                world.mode = mode;
+                world.add_force(ForceField::Gravity(DVec2::new(0.0, 5.0)));
+                world.add_force(ForceField::Harmonic {
+                    center: DVec2::new(1000.0, 1000.0),
+                    stiffness: 5.0,
+                });
+                world.add_force(ForceField::InverseDistance {
+                    center: DVec2::new(900.0, 1100.0),
+                    strength: 1e6,
+                    softening: 20.0,
+                });
+                let pos = DVec2::new(800.0 + 100.0 * k as f64, 1200.0 - 80.0 * k as f64);
+                let vel = DVec2::new(90.0 - 50.0 * k as f64, 30.0 * k as f64 - 60.0);
+                world.add_ball(Ball::new(pos, vel, 5.0, [1.0; 4]));
+
+                let energy = world.kinetic_energy() + world.potential_energy();
+                for _ in 0..6000 {
+                    world.update(1.0 / 60.0);
+                    let now = world.kinetic_energy() + world.potential_energy();
+                    assert!(
+                        (now / energy - 1.0).abs() < 1e-4,
+                        "{mode:?}, ball {k}: {now} vs {energy}"
+                    );
+                }
+                let pos = world.balls[0].pos;
+                assert!(pos.min_element() > 100.0 && pos.max_element() < 1900.0);
+            }
+        }
+    }
+
+    #[test]
+    fn balls_collide_across_periodic_seam() {
+        for mode in [Mode::TimeStepped, Mode::EventDriven] {
+            // Equal balls 20 apart through the seam at x = 0, closing at 20
+            let mut world = World::<f64>::new(100.0, 100.0);
+            world.mode = mode;
+            world.boundary = Boundary::Periodic;
+            world.add_ball(Ball::new(
+                DVec2::new(15.0, 50.0),
+                DVec2::new(-10.0, 0.0),
+                5.0,
+                [1.0; 4],
+            ));
+            world.add_ball(Ball::new(
+                DVec2::new(85.0, 50.0),
+                DVec2::new(10.0, 0.0),
+                5.0,
+                [1.0; 4],
+            ));
+
+            for _ in 0..4 {
+                world.update(0.5);
+            }
+
+            // Velocities swap at t = 1 and the balls are back where they
+            // started, neither having wrapped
+            let (a, b) = (&world.balls[0], &world.balls[1]);
+            assert!((a.vel - DVec2::new(10.0, 0.0)).length() < 1e-9, "{mode:?}");
+            assert!((b.vel - DVec2::new(-10.0, 0.0)).length() < 1e-9, "{mode:?}");
+            assert!(
+                (a.pos - DVec2::new(15.0, 50.0)).length() < 0.5,
+                "{:?}",
+                a.pos
+            );
+            assert!(
+                (b.pos - DVec2::new(85.0, 50.0)).length() < 0.5,
+                "{:?}",
+                b.pos
+            );
+            assert_eq!((a.image, b.image), (IVec2::ZERO, IVec2::ZERO));
+        }
+    }
+
+    #[test]
+    fn wrapping_keeps_balls_in_box_and_conserves_momentum() {
+        for mode in [Mode::TimeStepped, Mode::EventDriven] {
+            let mut world = gas::<f64>();
+            world.mode = mode;
+            world.boundary = Boundary::Periodic;
+            let momentum =
+                |world: &World<f64>| -> DVec2 { world.balls.iter().map(|b| b.vel * b.mass).sum() };
+            let initial = momentum(&world);
+
+            for _ in 0..2000 {
+                world.update(1.0 / 60.0);
+                for ball in &world.balls {
+                    assert!(
+                        ball.pos.x >= 0.0 && ball.pos.x < world.width,
+                        "{:?}",
+                        ball.pos
+                    );
+                    assert!(
+                        ball.pos.y >= 0.0 && ball.pos.y < world.height,
+                        "{:?}",
+                        ball.pos
+                    );
+                }
+            }
+
+            assert!((momentum(&world) - initial).length() < 1e-9 * initial.length());
+            // Unwrapped positions keep track of the trips around the box
+            let wraps: i32 = world
+                .balls
+                .iter()
+                .map(|b| b.image.abs().element_sum())
+                .sum();
+            assert!(wraps > 50, "{mode:?}: {wraps} wraps");
+        }
+    }
+
+    /// 100 disks of radius 1 on a square lattice in a 200 × 200 box.
+    fn dilute_gas(mode: Mode, seed: u64) -> World<f64> {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut world = World::<f64>::new(200.0, 200.0);
+        world.mode = mode;
+        for k in 0..100 {
+            let pos = DVec2::new(10.0 + 20.0 * (k % 10) as f64, 10.0 + 20.0 * (k / 10) as f64);
+            let vel = DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * 50.0;
+            world.add_ball(Ball::new(pos, vel, 1.0, [1.0; 4]));
+        }
+        world
+    }
+
+    #[test]
+    fn slow_compression_follows_two_dimensional_adiabat() {
+        let mut world = dilute_gas(Mode::EventDriven, 6);
+        world.add_piston(Piston::prescribed(Side::Right, 200.0, 0.0));
+        let measure = |world: &mut World<f64>| {
+            for _ in 0..10 {
+                world.update(1.0);
+            }
+            world.reset_pressure();
+            for _ in 0..20 {
+                world.update(1.0);
+            }
+            world.pressure() * world.area().powi(2)
+        };
+
+        let before = measure(&mut world);
+        // Halve the area far slower than the thermal speed of about 40
+        world.pistons[0].velocity = -0.5;
+        world.invalidate_events();
+        for _ in 0..200 {
+            world.update(1.0);
+        }
+        world.pistons[0].velocity = 0.0;
+        world.invalidate_events();
+        assert!((world.pistons[0].position - 100.0).abs() < 1e-9);
+        let after = measure(&mut world);
+
+        // γ = 2 for a 2D gas; an isothermal squeeze would halve P A². The
+        // disks' excluded area, which doubles in relative terms, adds about
+        // 5%
+        assert!(
+            (after / before - 1.0).abs() < 0.1,
+            "P A² {before} before, {after} after"
+        );
+    }
+
+    #[test]
+    fn massive_piston_settles_where_gas_pressure_balances_force() {
+        for mode in [Mode::TimeStepped, Mode::EventDriven] {
+            // Starting close to balance keeps the piston's slowly damped
+            // oscillation small, and a heavy piston barely jitters
+            let mut world = dilute_gas(mode, 7);
+            let force = world.kinetic_energy() / 200.0;
+            world.add_piston(Piston::massive(Side::Right, 200.0, 400.0, force));
+
+            for _ in 0..50 {
+                world.update(1.0);
+            }
+            let samples = 2000;
+            let (mut position, mut nkt) = (0.0, 0.0);
+            for _ in 0..samples {
+                world.update(0.1);
+                position += world.pistons[0].position / samples as f64;
+                // Two degrees of freedom per ball: N kT = E
+                nkt += world.kinetic_energy() / samples as f64;
+            }
+
+            // Ideal gas pushing on a wall of length H = 200, its pressure
+            // set by the area the ball centres can reach: F = N kT H /
+            // ((x - 2r)(H - 2r))
+            let balance = 2.0 + nkt * 200.0 / (force * 198.0);
+            assert!(
+                (position / balance - 1.0).abs() < 0.05,
+                "{mode:?}: piston at {position}, balance at {balance}"
+            );
+        }
+    }
+
+    #[test]
+    fn massive_piston_stays_in_box_through_periodic_spell() {
+        for mode in [Mode::TimeStepped, Mode::EventDriven] {
+            // Balanced well inside the box, released from its far end
+            let mut world = dilute_gas(mode, 9);
+            let force = world.kinetic_energy() / 120.0;
+            world.add_piston(Piston::massive(Side::Right, 200.0, 400.0, force));
+            for _ in 0..20 {
+                world.update(0.5);
+            }
+
+            world.boundary = Boundary::Periodic;
+            let frozen = world.pistons[0];
+            for _ in 0..20 {
+                world.update(0.5);
+            }
+            assert_eq!(world.pistons[0], frozen, "{mode:?}");
+
+            world.boundary = Boundary::Walls;
+            for _ in 0..20 {
+                world.update(0.5);
+                let x = world.pistons[0].position;
+                assert!((0.0..=world.width).contains(&x), "{mode:?}: piston at {x}");
+            }
+        }
+    }
+
+    #[test]
+    fn piston_work_equals_change_in_kinetic_energy() {
+        for mode in [Mode::TimeStepped, Mode::EventDriven] {
+            for piston in [
+                Piston::prescribed(Side::Right, 200.0, -20.0),
+                Piston::massive(Side::Bottom, 200.0, 50.0, 2000.0),
+            ] {
+                let mut world = dilute_gas(mode, 8);
+                world.add_piston(piston);
+                let initial = world.kinetic_energy();
+
+                for _ in 0..300 {
+                    world.update(1.0 / 60.0);
+                }
+
+                let work = world.pistons[0].work;
+                let gained = world.kinetic_energy() - initial;
+                assert!(work.abs() > 1e-3 * initial, "{mode:?}: work {work}");
+                assert!(
+                    (work - gained).abs() < 1e-9 * initial,
+                    "{mode:?}: work {work}, kinetic energy gained {gained}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_initial_conditions() {
+        let positions = |seed: u64| {
+            let mut world = World::<f64>::new(800.0, 600.0);
+            InitialConditions::new(seed).populate(&mut world, 10);
+            world
+                .balls
+                .iter()
+                .map(|b| (b.pos, b.vel, b.radius))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(positions(7), positions(7));
+        assert_ne!(positions(7), positions(8));
+    }
+
+    #[test]
+    fn seed_comes_from_flag_then_environment() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        assert_eq!(
+            requested_seed(&args(&["--seed", "42"]), Some("7")),
+            Ok(Some(42))
+        );
+        assert_eq!(requested_seed(&args(&[]), Some("7")), Ok(Some(7)));
+        assert_eq!(requested_seed(&args(&[]), None), Ok(None));
+        // A broken flag is an error rather than a silent fall-back
+        assert!(requested_seed(&args(&["--seed"]), Some("7")).is_err());
+        assert!(requested_seed(&args(&["--seed", "-3"]), None).is_err());
+        assert!(requested_seed(&args(&[]), Some("abc")).is_err());
+    }
+
+    #[test]
+    fn wall_pressure_matches_hard_disk_equation_of_state() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // 400 equal disks on a square lattice at area fraction ≈ 0.28
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut world = World::<f64>::new(400.0, 400.0);
+        world.mode = Mode::EventDriven;
+        for k in 0..400 {
+            let pos = DVec2::new(10.0 + 20.0 * (k % 20) as f64, 10.0 + 20.0 * (k / 20) as f64);
+            let vel = DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * 100.0;
+            world.add_ball(Ball::new(pos, vel, 6.0, [1.0; 4]));
+        }
+
+        for _ in 0..50 {
+            world.update(0.1);
+        }
+        world.reset_pressure();
+        for _ in 0..100 {
+            world.update(0.1);
+        }
+
+        let measured = world.pressure();
+        let henderson = world.henderson_pressure();
+        let ideal = world.ideal_gas_pressure();
+        assert!(
+            (measured / henderson - 1.0).abs() < 0.1,
+            "measured {measured}, Henderson {henderson}, ideal {ideal}"
+        );
+        for side in Side::ALL {
+            let p = world.wall_pressure(side);
+            assert!((p / measured - 1.0).abs() < 0.2, "{side:?}: {p} vs {measured}");
+        }
+    }
+
+    #[test]
+    fn free_flight_msd_is_ballistic_across_periodic_seams() {
+        // Tiny balls that never meet, fast enough to wrap many times
+        let mut world = World::<f64>::new(100.0, 100.0);
+        world.boundary = Boundary::Periodic;
+        let speeds = [30.0, 45.0, 60.0];
+        for (k, speed) in speeds.iter().enumerate() {
+            let pos = DVec2::new(10.0, 20.0 + 30.0 * k as f64);
+            world.add_ball(Ball::new(pos, DVec2::new(*speed, 0.0), 0.1, [1.0; 4]));
+        }
+        world.record_msd(MeanSquaredDisplacement::new(0.25, 20));
+
+        // Frame lengths that do not divide the sampling interval
+        for _ in 0..300 {
+            world.update(0.07);
+        }
+
+        let mean_v2 = speeds.iter().map(|v| v * v).sum::<f64>() / 3.0;
+        let values = world.msd().unwrap().values();
+        assert_eq!(values.len(), 20);
+        for (t, msd) in values {
+            assert!(
+                (msd / (mean_v2 * t * t) - 1.0).abs() < 1e-9,
+                "MSD({t}) = {msd}"
+            );
+        }
+    }
+
+    #[test]
+    fn hard_disk_diffusion_matches_enskog() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+
+        // 100 unit disks on a square lattice at area fraction 0.2, with
+        // kT / m = 1
+        let side = (100.0 * std::f64::consts::PI / 0.2).sqrt();
+        let spacing = side / 10.0;
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut world = World::<f64>::new(side, side);
+        world.mode = Mode::EventDriven;
+        world.boundary = Boundary::Periodic;
+        for k in 0..100 {
+            let pos = DVec2::new(
+                spacing * (0.5 + (k % 10) as f64),
+                spacing * (0.5 + (k / 10) as f64),
+            );
+            let vel = DVec2::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)) * 3f64.sqrt();
+            world.add_ball(Ball::new(pos, vel, 1.0, [1.0; 4]));
+        }
+
+        for _ in 0..20 {
+            world.update(1.0);
+        }
+        world.record_msd(MeanSquaredDisplacement::new(1.0, 30));
+        for _ in 0..400 {
+            world.update(1.0);
+        }
+
+        let msd = world.msd().unwrap();
+        let measured = msd.diffusion_coefficient(10.0..=msd.max_lag()).unwrap();
+        let enskog = world.enskog_diffusion();
+        // Correlated motion the Enskog theory leaves out raises D by 10-20%
+        // at this density
+        assert!(
+            (1.0..1.4).contains(&(measured / enskog)),
+            "measured {measured}, Enskog {enskog}"
+        );
     }
 }
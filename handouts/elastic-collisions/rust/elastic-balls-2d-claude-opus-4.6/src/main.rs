@@ -1,39 +1,78 @@
-use elastic_balls_2d::{Ball, World};
+use elastic_balls_2d::{
+    requested_seed, Ball, Boundary, ForceField, InitialConditions, MeanSquaredDisplacement, Mode,
+    Piston, PistonDrive, Side, World,
+};
 use macroquad::prelude::*;
-use ::rand::Rng;
-
-fn random_ball(width: f32, height: f32) -> Ball {
-    let mut rng = ::rand::thread_rng();
-    let radius = rng.gen_range(10.0..40.0);
-    let pos = Vec2::new(
-        rng.gen_range(radius..width - radius),
-        rng.gen_range(radius..height - radius),
-    );
-    let vel = Vec2::new(rng.gen_range(-300.0..300.0), rng.gen_range(-300.0..300.0));
-    let color = [
-        rng.gen_range(0.3..1.0),
-        rng.gen_range(0.3..1.0),
-        rng.gen_range(0.3..1.0),
-        1.0,
-    ];
-    Ball::new(pos, vel, radius, color)
+
+const INITIAL_BALLS: usize = 5;
+/// Simulated seconds of wall impacts averaged into each pressure reading.
+const PRESSURE_WINDOW: f64 = 2.0;
+/// Simulated seconds between MSD samples, and the number of lags kept.
+const MSD_INTERVAL: f64 = 0.1;
+const MSD_LAGS: usize = 50;
+
+/// Seed given as `--seed N` on the command line or in the `ELASTIC_SEED`
+/// environment variable, otherwise a fresh random one. The seed is shown in
+/// the HUD so the run can be replayed. Exits with a usage message if the seed
+/// given is not a non-negative integer.
+fn initial_seed() -> u64 {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env = std::env::var("ELASTIC_SEED").ok();
+    match requested_seed(&args, env.as_deref()) {
+        Ok(seed) => seed.unwrap_or_else(new_seed),
+        Err(message) => {
+            eprintln!("error: {message}");
+            eprintln!("usage: elastic-balls-2d [--seed N]");
+            std::process::exit(2);
+        }
+    }
 }
 
-#[macroquad::main("Elastic Balls 2D")]
-async fn main() {
-    let mut world = World::new(screen_width(), screen_height());
+/// Random seed, kept short enough to type back in.
+fn new_seed() -> u64 {
+    ::rand::random::<u32>() as u64
+}
+
+/// Offsets at which to draw `ball`: the ball itself plus, in a periodic box,
+/// ghost copies on the far side of every seam it straddles.
+fn image_offsets(world: &World<f64>, ball: &Ball<f64>) -> Vec<Vec2> {
+    let mut xs = vec![0.0];
+    let mut ys = vec![0.0];
+    let (width, height) = (world.width as f32, world.height as f32);
 
-    for _ in 0..5 {
-        let ball = random_ball(world.width, world.height);
-        world.add_ball(ball);
+    if world.boundary == Boundary::Periodic {
+        if ball.pos.x - ball.radius < 0.0 {
+            xs.push(width);
+        } else if ball.pos.x + ball.radius > world.width {
+            xs.push(-width);
+        }
+        if ball.pos.y - ball.radius < 0.0 {
+            ys.push(height);
+        } else if ball.pos.y + ball.radius > world.height {
+            ys.push(-height);
+        }
     }
 
+    xs.iter()
+        .flat_map(|&x| ys.iter().map(move |&y| Vec2::new(x, y)))
+        .collect()
+}
+
+#[macroquad::main("Elastic Balls 2D")]
+async fn main() {
+    // Simulate in double precision; only drawing uses f32
+    let mut world = World::<f64>::new(screen_width() as f64, screen_height() as f64);
+    let mut init = InitialConditions::new(initial_seed());
+    init.populate(&mut world, INITIAL_BALLS);
+    // Measured, ideal-gas and Henderson pressure over the last full window
+    let mut pressure = (0.0, 0.0, 0.0);
+
     loop {
         // Input
         if is_mouse_button_pressed(MouseButton::Left) {
             let (mx, my) = mouse_position();
-            let mut ball = random_ball(world.width, world.height);
-            ball.pos = Vec2::new(mx, my);
+            let mut ball: Ball<f64> = init.ball(world.width, world.height);
+            ball.pos = DVec2::new(mx as f64, my as f64);
             world.add_ball(ball);
         }
 
@@ -41,11 +80,84 @@ async fn main() {
             world.paused = !world.paused;
         }
 
+        // R replays the current seed, N starts over with a new one
         if is_key_pressed(KeyCode::R) {
-            world.clear();
-            for _ in 0..5 {
-                let ball = random_ball(world.width, world.height);
-                world.add_ball(ball);
+            init.populate(&mut world, INITIAL_BALLS);
+            if world.msd().is_some() {
+                world.record_msd(MeanSquaredDisplacement::new(MSD_INTERVAL, MSD_LAGS));
+            }
+        }
+
+        if is_key_pressed(KeyCode::N) {
+            init = InitialConditions::new(new_seed());
+            init.populate(&mut world, INITIAL_BALLS);
+            if world.msd().is_some() {
+                world.record_msd(MeanSquaredDisplacement::new(MSD_INTERVAL, MSD_LAGS));
+            }
+        }
+
+        // M starts recording the mean-squared displacement, or stops it
+        if is_key_pressed(KeyCode::M) && world.take_msd().is_none() {
+            world.record_msd(MeanSquaredDisplacement::new(MSD_INTERVAL, MSD_LAGS));
+        }
+
+        if is_key_pressed(KeyCode::E) {
+            world.mode = match world.mode {
+                Mode::TimeStepped => Mode::EventDriven,
+                Mode::EventDriven => Mode::TimeStepped,
+            };
+        }
+
+        if is_key_pressed(KeyCode::B) {
+            world.boundary = match world.boundary {
+                Boundary::Walls => Boundary::Periodic,
+                Boundary::Periodic => Boundary::Walls,
+            };
+            // Balls wrapping past a piston would end up behind it once the
+            // walls return
+            if world.boundary == Boundary::Periodic {
+                world.clear_pistons();
+            }
+        }
+
+        if is_key_pressed(KeyCode::G) {
+            if world.forces.is_empty() {
+                world.add_force(ForceField::Gravity(DVec2::new(0.0, 500.0)));
+            } else {
+                world.clear_forces();
+            }
+        }
+
+        // Pistons need walls to push against
+        if is_key_pressed(KeyCode::P) && world.boundary == Boundary::Walls {
+            if world.pistons.is_empty() {
+                // Barostat balancing the current ideal-gas pressure P = E / A
+                let pressure = world.ideal_gas_pressure();
+                let mass: f64 = world.balls.iter().map(|b| b.mass).sum();
+                world.add_piston(Piston::massive(
+                    Side::Right,
+                    world.width,
+                    mass.max(1.0),
+                    pressure * world.height,
+                ));
+            } else {
+                world.clear_pistons();
+            }
+        }
+
+        // Left/Right: lower/raise the piston's external pressure
+        let scale = if is_key_pressed(KeyCode::Right) {
+            1.25
+        } else if is_key_pressed(KeyCode::Left) {
+            0.8
+        } else {
+            1.0
+        };
+        if scale != 1.0 {
+            for piston in world.pistons.iter_mut() {
+                if let PistonDrive::Massive { force, .. } = &mut piston.drive {
+                    *force *= scale;
+                }
             }
         }
 
@@ -57,37 +169,101 @@ async fn main() {
         }
 
         // Resize
-        world.resize(screen_width(), screen_height());
+        world.resize(screen_width() as f64, screen_height() as f64);
 
         // Update
-        world.update(get_frame_time());
+        world.update(get_frame_time() as f64);
+        if world.sample_time() >= PRESSURE_WINDOW {
+            pressure = (
+                world.pressure(),
+                world.ideal_gas_pressure(),
+                world.henderson_pressure(),
+            );
+            world.reset_pressure();
+        }
 
         // Draw
         clear_background(Color::new(0.1, 0.1, 0.15, 1.0));
 
         // Boundary
-        draw_rectangle_lines(0.0, 0.0, world.width, world.height, 2.0, WHITE);
+        let (width, height) = (world.width as f32, world.height as f32);
+        draw_rectangle_lines(0.0, 0.0, width, height, 2.0, WHITE);
+
+        // Pistons
+        for piston in &world.pistons {
+            let position = piston.position as f32;
+            let (a, b) = match piston.side {
+                Side::Left | Side::Right => (Vec2::new(position, 0.0), Vec2::new(position, height)),
+                Side::Top | Side::Bottom => (Vec2::new(0.0, position), Vec2::new(width, position)),
+            };
+            draw_line(a.x, a.y, b.x, b.y, 6.0, ORANGE);
+        }
 
         // Balls
         for ball in &world.balls {
             let c = Color::new(ball.color[0], ball.color[1], ball.color[2], ball.color[3]);
-            draw_circle(ball.pos.x, ball.pos.y, ball.radius, c);
-            draw_circle_lines(ball.pos.x, ball.pos.y, ball.radius, 1.5, WHITE);
+            for offset in image_offsets(&world, ball) {
+                let pos = ball.pos.as_vec2() + offset;
+                let radius = ball.radius as f32;
+                draw_circle(pos.x, pos.y, radius, c);
+                draw_circle_lines(pos.x, pos.y, radius, 1.5, WHITE);
+            }
         }
 
         // HUD
         let hud = format!(
-            "Balls: {}  Speed: {:.1}x  FPS: {}{}",
+            "Balls: {}  Seed: {}  Speed: {:.1}x  Mode: {}  Boundary: {}{}{}  FPS: {}{}",
             world.ball_count(),
+            init.seed(),
             world.speed_multiplier,
+            match world.mode {
+                Mode::TimeStepped => "time-stepped",
+                Mode::EventDriven => "event-driven",
+            },
+            match world.boundary {
+                Boundary::Walls => "walls",
+                Boundary::Periodic => "periodic",
+            },
+            if world.forces.is_empty() { "" } else { "  Gravity" },
+            if world.pistons.is_empty() {
+                String::new()
+            } else {
+                format!("  Area: {:.0}", world.area())
+            },
             get_fps(),
             if world.paused { "  [PAUSED]" } else { "" },
         );
         draw_text(&hud, 10.0, 24.0, 20.0, WHITE);
+        if world.boundary == Boundary::Walls {
+            let eos = format!(
+                "Wall P: {:.1}  Ideal: {:.1}  Henderson: {:.1}  (phi = {:.3})",
+                pressure.0,
+                pressure.1,
+                pressure.2,
+                world.area_fraction(),
+            );
+            draw_text(&eos, 10.0, 48.0, 20.0, WHITE);
+        }
+        if let Some(msd) = world.msd() {
+            // Fit over the second half of the lags, past the ballistic start
+            let window = 0.5 * msd.max_lag()..=msd.max_lag();
+            let fitted = match msd.diffusion_coefficient(window) {
+                Some(d) => format!("{d:.0}"),
+                None => "-".to_string(),
+            };
+            let diffusion = format!(
+                "MSD D: {}  Enskog: {:.0}  (lags {:.1}-{:.1} s)",
+                fitted,
+                world.enskog_diffusion(),
+                0.5 * msd.max_lag(),
+                msd.max_lag(),
+            );
+            draw_text(&diffusion, 10.0, 72.0, 20.0, WHITE);
+        }
         draw_text(
-            "Click: add ball | Space: pause | R: reset | Up/Down: speed",
+            "Click: add ball | Space: pause | R: reset | N: new seed | E: mode | B: boundary | G: gravity | P: piston | M: MSD | Left/Right: pressure | Up/Down: speed",
             10.0,
-            world.height - 10.0,
+            height - 10.0,
             16.0,
             Color::new(0.7, 0.7, 0.7, 1.0),
         );
@@ -0,0 +1,113 @@
+use crate::{Ball, Float};
+
+/// One of the four sides of the box.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+impl Side {
+    pub const ALL: [Side; 4] = [Side::Left, Side::Right, Side::Top, Side::Bottom];
+
+    /// Coordinate axis the wall is perpendicular to (0 = x, 1 = y).
+    pub fn axis(self) -> usize {
+        match self {
+            Side::Left | Side::Right => 0,
+            Side::Top | Side::Bottom => 1,
+        }
+    }
+
+    /// Sign of the inward normal along `axis`.
+    pub fn inward<F: Float>(self) -> F {
+        match self {
+            Side::Left | Side::Top => F::ONE,
+            Side::Right | Side::Bottom => -F::ONE,
+        }
+    }
+}
+
+/// How a piston moves.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PistonDrive<F: Float = f32> {
+    /// Infinitely heavy: keeps its `velocity` whatever hits it. Change the
+    /// velocity between updates to follow a compression protocol.
+    Prescribed,
+    /// A free wall of the given mass, pressed into the box by a constant
+    /// external `force` (pressure × wall length for a barostat) and recoiling
+    /// from ball impacts.
+    Massive { mass: F, force: F },
+}
+
+/// A movable wall replacing one side of the box (only with
+/// `Boundary::Walls`). `position` and `velocity` are along the side's axis in
+/// world coordinates, so a right-hand piston at `x = 300` moving left has
+/// `position = 300.0` and a negative velocity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Piston<F: Float = f32> {
+    pub side: Side,
+    pub position: F,
+    pub velocity: F,
+    pub drive: PistonDrive<F>,
+    /// Energy the piston has transferred to the balls so far (negative when
+    /// the gas does work on the piston).
+    pub work: F,
+}
+
+impl<F: Float> Piston<F> {
+    pub fn prescribed(side: Side, position: F, velocity: F) -> Self {
+        Self {
+            side,
+            position,
+            velocity,
+            drive: PistonDrive::Prescribed,
+            work: F::ZERO,
+        }
+    }
+
+    /// A piston starting at rest.
+    pub fn massive(side: Side, position: F, mass: F, force: F) -> Self {
+        Self {
+            side,
+            position,
+            velocity: F::ZERO,
+            drive: PistonDrive::Massive { mass, force },
+            work: F::ZERO,
+        }
+    }
+
+    pub(crate) fn is_massive(&self) -> bool {
+        matches!(self.drive, PistonDrive::Massive { .. })
+    }
+
+    pub(crate) fn kick(&mut self, dt: F) {
+        if let PistonDrive::Massive { mass, force } = self.drive {
+            self.velocity += self.side.inward::<F>() * force / mass * dt;
+        }
+    }
+
+    /// Elastic collision with `ball`, worked out in the piston's rest frame.
+    /// Does nothing if the ball is already moving away from the piston.
+    pub(crate) fn collide(&mut self, ball: &mut Ball<F>) {
+        let axis = self.side.axis();
+        let inward: F = self.side.inward();
+
+        let approach = (ball.vel[axis] - self.velocity) * inward;
+        if approach >= F::ZERO {
+            return;
+        }
+
+        let inv_mass = match self.drive {
+            PistonDrive::Prescribed => F::ZERO,
+            PistonDrive::Massive { mass, .. } => F::ONE / mass,
+        };
+        let impulse = -F::TWO * approach / (F::ONE / ball.mass + inv_mass);
+
+        let before = ball.vel[axis];
+        ball.vel[axis] += inward * impulse / ball.mass;
+        self.velocity -= inward * impulse * inv_mass;
+        self.work += F::HALF * ball.mass * (ball.vel[axis] * ball.vel[axis] - before * before);
+    }
+}
@@ -0,0 +1,89 @@
+//! Wall pressure from the momentum the balls deliver to each side of the box,
+//! and the equations of state to compare it with (k_B = 1).
+
+use crate::{Float, Side, World};
+
+impl<F: Float> World<F> {
+    /// Credit the wall on `side` with a ball's momentum change `dp` along the
+    /// side's axis.
+    pub(crate) fn record_wall_impulse(&mut self, side: Side, dp: F) {
+        self.wall_impulse[side as usize] += dp * side.inward::<F>();
+    }
+
+    /// Start a new sampling window for the wall pressure.
+    pub fn reset_pressure(&mut self) {
+        self.wall_impulse = [F::ZERO; 4];
+        self.sample_time = F::ZERO;
+    }
+
+    /// Outward momentum the wall on `side` has received since the last
+    /// `reset_pressure`.
+    pub fn wall_impulse(&self, side: Side) -> F {
+        self.wall_impulse[side as usize]
+    }
+
+    /// Simulated time since the last `reset_pressure`.
+    pub fn sample_time(&self) -> F {
+        self.sample_time
+    }
+
+    /// Length of the wall on `side`, between the two walls perpendicular to
+    /// it.
+    pub fn wall_length(&self, side: Side) -> F {
+        let (low, high) = match side.axis() {
+            0 => (Side::Top, Side::Bottom),
+            _ => (Side::Left, Side::Right),
+        };
+        (self.wall(high).0 - self.wall(low).0).max(F::ZERO)
+    }
+
+    /// Time-averaged force per unit length on the wall on `side` over the
+    /// current window. Zero before any time has passed.
+    pub fn wall_pressure(&self, side: Side) -> F {
+        let length = self.wall_length(side);
+        if self.sample_time <= F::ZERO || length <= F::ZERO {
+            return F::ZERO;
+        }
+        self.wall_impulse(side) / (self.sample_time * length)
+    }
+
+    /// Pressure averaged over all four walls: total impulse over time and
+    /// perimeter.
+    pub fn pressure(&self) -> F {
+        let perimeter: F = Side::ALL.iter().map(|&s| self.wall_length(s)).sum();
+        if self.sample_time <= F::ZERO || perimeter <= F::ZERO {
+            return F::ZERO;
+        }
+        let impulse: F = self.wall_impulse.iter().copied().sum();
+        impulse / (self.sample_time * perimeter)
+    }
+
+    /// Fraction of the enclosed area covered by the balls.
+    pub fn area_fraction(&self) -> F {
+        let area = self.area();
+        if area <= F::ZERO {
+            return F::ZERO;
+        }
+        let covered: F = self.balls.iter().map(|b| F::PI * b.radius * b.radius).sum();
+        covered / area
+    }
+
+    /// Ideal-gas pressure `N kT / A` at the current kinetic energy; in 2D
+    /// `N kT` is the kinetic energy itself.
+    pub fn ideal_gas_pressure(&self) -> F {
+        let area = self.area();
+        if area <= F::ZERO {
+            return F::ZERO;
+        }
+        self.kinetic_energy() / area
+    }
+
+    /// Henderson's hard-disk equation of state,
+    /// `P A / N kT = (1 + φ²/8) / (1 - φ)²` with `φ` the area fraction.
+    pub fn henderson_pressure(&self) -> F {
+        let phi = self.area_fraction();
+        let one_minus = F::ONE - phi;
+        self.ideal_gas_pressure() * (F::ONE + phi * phi / F::from_f64(8.0))
+            / (one_minus * one_minus)
+    }
+}
@@ -1,11 +1,21 @@
-use ::glam::Vec2;
-use elastic_balls_2d::{Ball, World};
+use ::glam::{Vec2, Vec3};
+use ::rand::SeedableRng;
+use ::rand::rngs::StdRng;
+use elastic_balls_2d::{
+    Ball, CollisionLog, Friction, RadialDistribution, Side, Vector, Wall, World, placement,
+};
 use macroquad::prelude::*;
 use macroquad::rand::gen_range;
 
 const WIDTH: f32 = 1000.0;
 const HEIGHT: f32 = 700.0;
 const BALL_COUNT: usize = 36;
+const BOX_SIZE: f32 = 500.0;
+const SPHERE_COUNT: usize = 150;
+const PRESSURE_WINDOW: f32 = 2.0;
+const RDF_FILE: &str = "rdf.dat";
+const BACKGROUND: Color = Color::new(14.0 / 255.0, 18.0 / 255.0, 25.0 / 255.0, 1.0);
+const OUTLINE: Color = Color::new(120.0 / 255.0, 140.0 / 255.0, 170.0 / 255.0, 1.0);
 
 fn random_color() -> Color {
     Color::new(
@@ -17,89 +27,314 @@ fn random_color() -> Color {
 }
 
 fn random_world() -> (World, Vec<Color>) {
-    let mut balls = Vec::with_capacity(BALL_COUNT);
-    let mut colors = Vec::with_capacity(BALL_COUNT);
+    let radii: Vec<f32> = (0..BALL_COUNT).map(|_| gen_range(8.0, 16.0)).collect();
 
-    for _ in 0..BALL_COUNT {
-        let radius = gen_range(8.0, 16.0);
-        let mass = radius * radius;
+    // Random sequential addition, with compression as a fallback should the
+    // box ever be too crowded for it
+    let mut rng = StdRng::seed_from_u64(macroquad::rand::rand() as u64);
+    let positions = placement::random_sequential(WIDTH, HEIGHT, &radii, 200, &mut rng)
+        .or_else(|_| placement::compress(WIDTH, HEIGHT, &radii, &mut rng))
+        .expect("balls cover only a few percent of the window");
 
-        let mut placed = false;
-        for _ in 0..200 {
-            let position = Vec2::new(
-                gen_range(radius, WIDTH - radius),
-                gen_range(radius, HEIGHT - radius),
-            );
+    let balls = radii
+        .iter()
+        .zip(positions)
+        .map(|(&radius, position)| {
+            let velocity = Vec2::new(gen_range(-180.0, 180.0), gen_range(-180.0, 180.0));
+            Ball::new(position, velocity, radius, radius * radius)
+        })
+        .collect();
+    let colors = (0..BALL_COUNT).map(|_| random_color()).collect();
 
-            let overlaps = balls.iter().any(|b: &Ball| {
-                (b.position - position).length_squared() < (b.radius + radius).powi(2)
-            });
+    (World::new(WIDTH, HEIGHT, balls), colors)
+}
 
-            if overlaps {
-                continue;
-            }
+fn random_world_3d() -> (World<Vec3>, Vec<Color>) {
+    let mut balls: Vec<Ball<Vec3>> = Vec::with_capacity(SPHERE_COUNT);
+    let mut colors = Vec::with_capacity(SPHERE_COUNT);
 
-            let velocity = Vec2::new(gen_range(-180.0, 180.0), gen_range(-180.0, 180.0));
-            balls.push(Ball {
-                position,
-                velocity,
-                radius,
-                mass,
-            });
-            colors.push(random_color());
-            placed = true;
+    // Random sequential addition with a bounded number of tries, so an
+    // overfull box ends up with fewer spheres instead of hanging
+    for _ in 0..200 * SPHERE_COUNT {
+        if balls.len() == SPHERE_COUNT {
             break;
         }
+        let radius = gen_range(8.0, 16.0);
+        let position = Vec3::new(
+            gen_range(radius, BOX_SIZE - radius),
+            gen_range(radius, BOX_SIZE - radius),
+            gen_range(radius, BOX_SIZE - radius),
+        );
+        if balls
+            .iter()
+            .any(|b| (b.position - position).length_squared() < (b.radius + radius).powi(2))
+        {
+            continue;
+        }
+
+        let velocity = Vec3::new(
+            gen_range(-180.0, 180.0),
+            gen_range(-180.0, 180.0),
+            gen_range(-180.0, 180.0),
+        );
+        balls.push(Ball::new(position, velocity, radius, radius.powi(3)));
+        colors.push(random_color());
+    }
 
-        if !placed {
-            let position = Vec2::new(
-                gen_range(radius, WIDTH - radius),
-                gen_range(radius, HEIGHT - radius),
-            );
-            balls.push(Ball {
-                position,
-                velocity: Vec2::new(gen_range(-180.0, 180.0), gen_range(-180.0, 180.0)),
-                radius,
-                mass,
-            });
-            colors.push(random_color());
+    (World::with_size(Vec3::splat(BOX_SIZE), balls), colors)
+}
+
+fn draw_flat(world: &World, colors: &[Color]) {
+    draw_rectangle_lines(0.0, 0.0, WIDTH, HEIGHT, 2.0, OUTLINE);
+
+    // Thermal walls: hot on the left, cold on the right
+    for (side, x, color) in [
+        (Side::Left, 0.0, Color::from_rgba(235, 90, 70, 255)),
+        (Side::Right, WIDTH, Color::from_rgba(80, 140, 235, 255)),
+    ] {
+        if world.walls[side as usize] != Wall::Specular {
+            draw_line(x, 0.0, x, HEIGHT, 6.0, color);
         }
     }
 
+    for (idx, ball) in world.balls.iter().enumerate() {
+        draw_circle(ball.position.x, ball.position.y, ball.radius, colors[idx]);
+
+        // Orientation marker so spin is visible
+        let (sin, cos) = ball.orientation.sin_cos();
+        draw_line(
+            ball.position.x,
+            ball.position.y,
+            ball.position.x + ball.radius * cos,
+            ball.position.y + ball.radius * sin,
+            2.0,
+            BACKGROUND,
+        );
+    }
+}
+
+/// Orthographic view of the box turned by `yaw` about the vertical (y)
+/// axis and tilted slightly towards the viewer. Returns the screen position
+/// and the depth (larger is farther away).
+fn project(world: &World<Vec3>, point: Vec3, yaw: f32) -> (Vec2, f32) {
+    const PITCH: f32 = 0.45;
+    let scale = 0.5 * HEIGHT / world.size.max_element();
+    let p = point - 0.5 * world.size;
+    let (sin_yaw, cos_yaw) = yaw.sin_cos();
+    let (sin_pitch, cos_pitch) = PITCH.sin_cos();
+    let x = p.x * cos_yaw - p.z * sin_yaw;
+    let z = p.x * sin_yaw + p.z * cos_yaw;
+    let y = p.y * cos_pitch - z * sin_pitch;
+    let depth = p.y * sin_pitch + z * cos_pitch;
     (
-        World {
-            width: WIDTH,
-            height: HEIGHT,
-            balls,
-        },
-        colors,
+        Vec2::new(0.5 * WIDTH, 0.5 * HEIGHT) + scale * Vec2::new(x, y),
+        depth,
+    )
+}
+
+fn draw_solid(world: &World<Vec3>, colors: &[Color], yaw: f32) {
+    let scale = 0.5 * HEIGHT / world.size.max_element();
+    let corner = |k: usize| {
+        let unit = Vec3::new((k & 1) as f32, (k >> 1 & 1) as f32, (k >> 2 & 1) as f32);
+        project(world, unit * world.size, yaw).0
+    };
+
+    // Box edges join corners that differ in one coordinate
+    for k in 0..8 {
+        for bit in [1, 2, 4] {
+            if k & bit == 0 {
+                let (a, b) = (corner(k), corner(k | bit));
+                draw_line(a.x, a.y, b.x, b.y, 1.5, OUTLINE);
+            }
+        }
+    }
+
+    // Painter's algorithm: far spheres first, dimmed with depth
+    let half_depth = 0.9 * world.size.max_element();
+    let mut order: Vec<(usize, Vec2, f32)> = world
+        .balls
+        .iter()
+        .enumerate()
+        .map(|(idx, ball)| {
+            let (screen, depth) = project(world, ball.position, yaw);
+            (idx, screen, depth)
+        })
+        .collect();
+    order.sort_by(|a, b| b.2.total_cmp(&a.2));
+
+    for (idx, screen, depth) in order {
+        let ball = &world.balls[idx];
+        let shade = 0.8 - 0.3 * (depth / half_depth).clamp(-1.0, 1.0);
+        let base = colors[idx];
+        let color = Color::new(base.r * shade, base.g * shade, base.b * shade, 1.0);
+        let radius = ball.radius * scale;
+        draw_circle(screen.x, screen.y, radius, color);
+
+        // Orientation marker: the ball's rotated x axis
+        let (tip, _) = project(
+            world,
+            ball.position + ball.orientation * Vec3::X * ball.radius,
+            yaw,
+        );
+        draw_line(screen.x, screen.y, tip.x, tip.y, 1.5, BACKGROUND);
+    }
+}
+
+fn toggle_friction<V: Vector>(world: &mut World<V>) {
+    world.friction = if world.friction == Friction::SMOOTH {
+        Friction::ROUGH
+    } else {
+        Friction::SMOOTH
+    };
+}
+
+fn toggle_thermal_walls<V: Vector>(world: &mut World<V>) {
+    if world.walls[Side::Left as usize] == Wall::Specular {
+        // Reservoirs at twice and half the current temperature
+        let temperature = world.temperature();
+        world.set_wall(
+            Side::Left,
+            Wall::Thermal {
+                temperature: 2.0 * temperature,
+            },
+        );
+        world.set_wall(
+            Side::Right,
+            Wall::Thermal {
+                temperature: 0.5 * temperature,
+            },
+        );
+    } else {
+        world.walls = [Wall::Specular; 6];
+    }
+    world.heat = [0.0; 6];
+}
+
+/// Start accumulating g(r) out to four mean diameters, or stop and write it
+/// to `RDF_FILE`.
+fn toggle_rdf<V: Vector>(world: &mut World<V>) {
+    match world.take_rdf() {
+        Some(rdf) => {
+            if let Err(err) = rdf.save(RDF_FILE) {
+                eprintln!("could not write {RDF_FILE}: {err}");
+            }
+        }
+        None => {
+            let radius =
+                world.balls.iter().map(|b| b.radius).sum::<f32>() / world.balls.len().max(1) as f32;
+            world.record_rdf(RadialDistribution::new(8.0 * radius, 80, 2));
+        }
+    }
+}
+
+fn toggle_collision_log<V: Vector>(world: &mut World<V>) {
+    if world.take_collision_log().is_none() {
+        world.record_collisions(CollisionLog::new());
+    }
+}
+
+fn status<V: Vector>(world: &World<V>) -> String {
+    let contacts = if world.friction == Friction::SMOOTH {
+        "smooth"
+    } else {
+        "rough"
+    };
+    let thermal = if world.walls[Side::Left as usize] == Wall::Specular {
+        "off".to_string()
+    } else {
+        format!(
+            "heat in {:.0} / out {:.0}",
+            world.heat_exchanged(Side::Left),
+            -world.heat_exchanged(Side::Right)
+        )
+    };
+    format!("F: contacts ({contacts})   T: thermal walls ({thermal})")
+}
+
+/// State of the g(r) and collision-log recorders.
+fn recorders<V: Vector>(world: &World<V>) -> String {
+    let rdf = match world.rdf() {
+        Some(rdf) => format!("{} frames", rdf.frames()),
+        None => "off".to_string(),
+    };
+    let collisions = match world.collision_log() {
+        Some(log) if log.total() > 0 => format!(
+            "mfp {:.0} / theory {:.0}",
+            log.mean_free_path(),
+            world.kinetic_mean_free_path()
+        ),
+        Some(_) => "on".to_string(),
+        None => "off".to_string(),
+    };
+    format!("G: g(r) ({rdf})   L: collisions ({collisions})")
+}
+
+/// Energy, temperature and pressure, the last averaged over windows of
+/// `PRESSURE_WINDOW` seconds; `pressure` holds the latest complete window.
+fn observables<V: Vector>(world: &mut World<V>, pressure: &mut f32) -> String {
+    if world.pressure_window().duration >= PRESSURE_WINDOW {
+        *pressure = world.pressure();
+        world.reset_pressure();
+    }
+    format!(
+        "E: {:.0}   kT: {:.0}   P: {:.4}",
+        world.kinetic_energy(),
+        world.temperature(),
+        pressure
     )
 }
 
 #[macroquad::main("Elastic Balls 2D")]
 async fn main() {
     let (mut world, mut colors) = random_world();
+    let mut solid: Option<(World<Vec3>, Vec<Color>)> = None;
+    let mut yaw = 0.6_f32;
+    let mut pressure = 0.0;
 
     loop {
         let dt = get_frame_time().min(1.0 / 30.0);
-        world.step(dt);
-
-        clear_background(Color::from_rgba(14, 18, 25, 255));
-        draw_rectangle_lines(
-            0.0,
-            0.0,
-            WIDTH,
-            HEIGHT,
-            2.0,
-            Color::from_rgba(120, 140, 170, 255),
+
+        clear_background(BACKGROUND);
+        let (status, recorders, observables) = match &mut solid {
+            Some((world, colors)) => {
+                world.step(dt);
+                yaw += 0.2 * dt;
+                draw_solid(world, colors, yaw);
+                (
+                    status(world),
+                    recorders(world),
+                    observables(world, &mut pressure),
+                )
+            }
+            None => {
+                world.step(dt);
+                draw_flat(&world, &colors);
+                (
+                    status(&world),
+                    recorders(&world),
+                    observables(&mut world, &mut pressure),
+                )
+            }
+        };
+
+        draw_text(
+            &observables,
+            16.0,
+            28.0,
+            24.0,
+            Color::from_rgba(220, 226, 236, 255),
         );
 
-        for (idx, ball) in world.balls.iter().enumerate() {
-            draw_circle(ball.position.x, ball.position.y, ball.radius, colors[idx]);
-        }
+        draw_text(
+            &recorders,
+            16.0,
+            HEIGHT - 40.0,
+            24.0,
+            Color::from_rgba(220, 226, 236, 255),
+        );
 
         draw_text(
-            "R: respawn   ESC: quit",
+            &format!("R: respawn   D: 2D/3D   {status}   ESC: quit"),
             16.0,
             HEIGHT - 12.0,
             24.0,
@@ -107,9 +342,57 @@ async fn main() {
         );
 
         if is_key_pressed(KeyCode::R) {
-            let (new_world, new_colors) = random_world();
-            world = new_world;
-            colors = new_colors;
+            match &mut solid {
+                Some((world, colors)) => {
+                    let (mut new_world, new_colors) = random_world_3d();
+                    new_world.friction = world.friction;
+                    new_world.walls = world.walls;
+                    *world = new_world;
+                    *colors = new_colors;
+                }
+                None => {
+                    let (mut new_world, new_colors) = random_world();
+                    new_world.friction = world.friction;
+                    new_world.walls = world.walls;
+                    world = new_world;
+                    colors = new_colors;
+                }
+            }
+        }
+
+        if is_key_pressed(KeyCode::D) {
+            solid = match solid {
+                Some(_) => None,
+                None => Some(random_world_3d()),
+            };
+        }
+
+        if is_key_pressed(KeyCode::F) {
+            match &mut solid {
+                Some((world, _)) => toggle_friction(world),
+                None => toggle_friction(&mut world),
+            }
+        }
+
+        if is_key_pressed(KeyCode::T) {
+            match &mut solid {
+                Some((world, _)) => toggle_thermal_walls(world),
+                None => toggle_thermal_walls(&mut world),
+            }
+        }
+
+        if is_key_pressed(KeyCode::G) {
+            match &mut solid {
+                Some((world, _)) => toggle_rdf(world),
+                None => toggle_rdf(&mut world),
+            }
+        }
+
+        if is_key_pressed(KeyCode::L) {
+            match &mut solid {
+                Some((world, _)) => toggle_collision_log(world),
+                None => toggle_collision_log(&mut world),
+            }
         }
 
         if is_key_pressed(KeyCode::Escape) {
@@ -0,0 +1,101 @@
+use crate::{Ball, Vector};
+
+/// Pairs `(i, j)` with `i < j` whose balls sit in the same or adjacent cells
+/// of a uniform grid, sorted so they come out in brute-force loop order.
+///
+/// Cells are at least as wide as the largest possible contact distance
+/// (twice the largest radius) and as `reach`, so every touching pair and
+/// every pair closer than `reach` is included.
+pub(crate) fn candidate_pairs<V: Vector>(
+    balls: &[Ball<V>],
+    size: V,
+    reach: f32,
+) -> Vec<(usize, usize)> {
+    let len = balls.len();
+    if len < 2 {
+        return Vec::new();
+    }
+
+    let max_radius = balls.iter().map(|b| b.radius).fold(0.0_f32, f32::max);
+    let cell_size = (2.0 * max_radius).max(reach).max(f32::EPSILON);
+
+    // More cells than balls only costs memory, so cap the grid there.
+    let max_cells = (len as f32).powf(1.0 / V::DIM as f32).ceil() as usize;
+    let mut dims = [1usize; 3];
+    let mut cell = [1.0_f32; 3];
+    for axis in 0..V::DIM {
+        dims[axis] = ((size[axis] / cell_size).floor() as usize).clamp(1, max_cells);
+        cell[axis] = size[axis] / dims[axis] as f32;
+    }
+    let cell_count = dims.iter().product::<usize>();
+    let index = |c: [usize; 3]| c[0] + dims[0] * (c[1] + dims[1] * c[2]);
+
+    let cell_of = |ball: &Ball<V>| {
+        let mut c = [0usize; 3];
+        for axis in 0..V::DIM {
+            c[axis] =
+                ((ball.position[axis] / cell[axis]).floor().max(0.0) as usize).min(dims[axis] - 1);
+        }
+        c
+    };
+
+    // Counting sort of ball indices by cell.
+    let cells: Vec<usize> = balls.iter().map(|b| index(cell_of(b))).collect();
+    let mut start = vec![0usize; cell_count + 1];
+    for &c in &cells {
+        start[c + 1] += 1;
+    }
+    for c in 0..cell_count {
+        start[c + 1] += start[c];
+    }
+    let mut fill = start.clone();
+    let mut sorted = vec![0usize; len];
+    for (i, &c) in cells.iter().enumerate() {
+        sorted[fill[c]] = i;
+        fill[c] += 1;
+    }
+    let members = |c: usize| &sorted[start[c]..start[c + 1]];
+
+    // Half stencil: of each pair of opposite neighbour offsets keep the one
+    // whose last non-zero component is positive, so each neighbouring cell
+    // pair is visited exactly once.
+    let forward: Vec<[isize; 3]> = (0..3_usize.pow(V::DIM as u32))
+        .map(|code| {
+            let mut offset = [0isize; 3];
+            for (axis, o) in offset.iter_mut().enumerate().take(V::DIM) {
+                *o = (code / 3_usize.pow(axis as u32) % 3) as isize - 1;
+            }
+            offset
+        })
+        .filter(|offset| offset.iter().rev().find(|&&o| o != 0) == Some(&1))
+        .collect();
+
+    let mut pairs = Vec::new();
+    for c in 0..cell_count {
+        let here = members(c);
+        for (k, &i) in here.iter().enumerate() {
+            for &j in &here[k + 1..] {
+                pairs.push((i.min(j), i.max(j)));
+            }
+        }
+
+        let coords = [c % dims[0], c / dims[0] % dims[1], c / (dims[0] * dims[1])];
+        'stencil: for offset in &forward {
+            let mut other = [0usize; 3];
+            for axis in 0..3 {
+                match coords[axis].checked_add_signed(offset[axis]) {
+                    Some(o) if o < dims[axis] => other[axis] = o,
+                    _ => continue 'stencil,
+                }
+            }
+            for &i in here {
+                for &j in members(index(other)) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+    }
+
+    pairs.sort_unstable();
+    pairs
+}
@@ -0,0 +1,194 @@
+//! Log of the ball-ball collisions resolved by `World::step`, with the
+//! collision statistics of kinetic theory: collision frequency, mean free
+//! time and mean free path.
+
+use crate::{Vector, World};
+use glam::Vec2;
+use std::f32::consts::{PI, SQRT_2};
+
+/// One resolved ball-ball collision.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Collision<V: Vector = Vec2> {
+    /// Simulated time at the end of the step that resolved it.
+    pub time: f64,
+    /// Indices of the two balls, `i < j`.
+    pub i: usize,
+    pub j: usize,
+    /// Middle of the overlap on the line of centres.
+    pub point: V,
+    /// Unit normal from ball `i` towards ball `j`.
+    pub normal: V,
+    /// Total impulse on ball `j`, normal plus tangential; ball `i` receives
+    /// the opposite.
+    pub impulse: V,
+    /// Speed of `j` relative to `i` just before the collision.
+    pub relative_speed: f32,
+}
+
+/// Collision counts and free-flight totals accumulated while attached with
+/// `World::record_collisions`, optionally keeping every `Collision` as well.
+///
+/// Statistics only cover the steps taken while the log was attached, and
+/// ball indices are taken to be stable over that time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollisionLog<V: Vector = Vec2> {
+    /// Events not yet drained, if the log keeps them.
+    events: Option<Vec<Collision<V>>>,
+    collisions: u64,
+    /// Collisions per ball.
+    counts: Vec<u64>,
+    /// Simulated time covered.
+    duration: f64,
+    /// Summed ball-time, `Σ N dt`.
+    ball_time: f64,
+    /// Summed distance travelled by all balls.
+    path: f64,
+}
+
+impl<V: Vector> CollisionLog<V> {
+    /// A log that only accumulates the statistics.
+    pub fn new() -> Self {
+        Self {
+            events: None,
+            collisions: 0,
+            counts: Vec::new(),
+            duration: 0.0,
+            ball_time: 0.0,
+            path: 0.0,
+        }
+    }
+
+    /// A log that also keeps every collision until `drain_events`.
+    pub fn with_events() -> Self {
+        Self {
+            events: Some(Vec::new()),
+            ..Self::new()
+        }
+    }
+
+    /// Hand out the collisions kept since the last call, oldest first. Empty
+    /// for a log made with `new`.
+    pub fn drain_events(&mut self) -> Vec<Collision<V>> {
+        self.events.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Collisions kept and not yet drained.
+    pub fn events(&self) -> &[Collision<V>] {
+        self.events.as_deref().unwrap_or_default()
+    }
+
+    pub(crate) fn record(&mut self, collision: Collision<V>) {
+        self.collisions += 1;
+        let needed = collision.j + 1;
+        if self.counts.len() < needed {
+            self.counts.resize(needed, 0);
+        }
+        self.counts[collision.i] += 1;
+        self.counts[collision.j] += 1;
+        if let Some(events) = &mut self.events {
+            events.push(collision);
+        }
+    }
+
+    /// Count a step of `dt` in which balls moved with `velocities`.
+    pub(crate) fn advance(&mut self, dt: f32, velocities: impl Iterator<Item = V>) {
+        let dt = f64::from(dt);
+        let (balls, speed) = velocities.fold((0, 0.0), |(n, s), v| {
+            (n + 1, s + f64::from(v.length_squared().sqrt()))
+        });
+        self.duration += dt;
+        self.ball_time += balls as f64 * dt;
+        self.path += speed * dt;
+    }
+
+    /// Number of ball-ball collisions.
+    pub fn total(&self) -> u64 {
+        self.collisions
+    }
+
+    /// Collisions each ball took part in, by index; balls past the end never
+    /// collided.
+    pub fn counts(&self) -> &[u64] {
+        &self.counts
+    }
+
+    /// Simulated time covered.
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    /// Collisions per ball per unit time, `2 C / (N t)`.
+    pub fn collision_frequency(&self) -> f64 {
+        if self.ball_time <= 0.0 {
+            return 0.0;
+        }
+        2.0 * self.collisions as f64 / self.ball_time
+    }
+
+    /// Mean time between two collisions of the same ball. Infinite before the
+    /// first collision.
+    pub fn mean_free_time(&self) -> f64 {
+        if self.collisions == 0 {
+            return f64::INFINITY;
+        }
+        1.0 / self.collision_frequency()
+    }
+
+    /// Mean distance a ball travels between two collisions: total path over
+    /// the number of collisions counted per ball. Infinite before the first
+    /// collision.
+    pub fn mean_free_path(&self) -> f64 {
+        if self.collisions == 0 {
+            return f64::INFINITY;
+        }
+        self.path / (2.0 * self.collisions as f64)
+    }
+}
+
+impl<V: Vector> Default for CollisionLog<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V: Vector> World<V> {
+    /// Start logging collisions into `log`, replacing any log already
+    /// attached.
+    pub fn record_collisions(&mut self, log: CollisionLog<V>) {
+        self.collision_log = Some(log);
+    }
+
+    pub fn collision_log(&self) -> Option<&CollisionLog<V>> {
+        self.collision_log.as_ref()
+    }
+
+    /// Mutable access to the attached log, e.g. to drain its events.
+    pub fn collision_log_mut(&mut self) -> Option<&mut CollisionLog<V>> {
+        self.collision_log.as_mut()
+    }
+
+    /// Stop logging and hand back the log.
+    pub fn take_collision_log(&mut self) -> Option<CollisionLog<V>> {
+        self.collision_log.take()
+    }
+
+    /// Dilute-gas mean free path from kinetic theory, `1 / (√2 n s)`, for
+    /// balls of the mean diameter `σ` at number density `n`. The cross
+    /// section `s` is `πσ²` for spheres and, for disks, the width `2σ` of the
+    /// strip a moving disk sweeps, so `λ = 1 / (2√2 n σ)`. At finite density,
+    /// divide by the contact value of the pair correlation (Enskog) to compare
+    /// with `CollisionLog::mean_free_path`.
+    pub fn kinetic_mean_free_path(&self) -> f32 {
+        if self.balls.is_empty() {
+            return f32::INFINITY;
+        }
+        let count = self.balls.len() as f32;
+        let diameter = 2.0 * self.balls.iter().map(|b| b.radius).sum::<f32>() / count;
+        let density = count / self.volume();
+        let cross_section = match V::DIM {
+            2 => 2.0 * diameter,
+            _ => PI * diameter * diameter,
+        };
+        1.0 / (SQRT_2 * density * cross_section)
+    }
+}
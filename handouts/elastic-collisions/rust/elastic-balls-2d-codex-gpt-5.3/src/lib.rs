@@ -1,197 +1,1033 @@
 use glam::Vec2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::TAU;
 
+mod cell_list;
+pub mod collisions;
+pub mod observables;
+pub mod placement;
+pub mod rdf;
+mod vector;
+
+pub use collisions::{Collision, CollisionLog};
+pub use observables::PressureWindow;
+pub use rdf::RadialDistribution;
+pub use vector::Vector;
+
+/// A disk (`V = Vec2`) or sphere (`V = Vec3`).
 #[derive(Debug, Clone)]
-pub struct Ball {
-    pub position: Vec2,
-    pub velocity: Vec2,
+pub struct Ball<V: Vector = Vec2> {
+    pub position: V,
+    pub velocity: V,
     pub radius: f32,
     pub mass: f32,
+    /// Rotation angle in radians in `[0, 2π)` for disks, a unit quaternion
+    /// for spheres.
+    pub orientation: V::Orientation,
+    /// Spin in radians per unit time; for disks positive when turning from +x
+    /// towards +y.
+    pub angular_velocity: V::Spin,
+    /// Moment of inertia about the centre.
+    pub moment_of_inertia: f32,
+}
+
+impl<V: Vector> Ball<V> {
+    /// A non-spinning uniform disk (moment of inertia ½ m r²) or sphere
+    /// (⅖ m r²).
+    pub fn new(position: V, velocity: V, radius: f32, mass: f32) -> Self {
+        Self {
+            position,
+            velocity,
+            radius,
+            mass,
+            orientation: V::IDENTITY,
+            angular_velocity: V::Spin::default(),
+            moment_of_inertia: V::INERTIA_FACTOR * mass * radius * radius,
+        }
+    }
+
+    fn inverse_inertia(&self) -> f32 {
+        if self.moment_of_inertia > 0.0 {
+            1.0 / self.moment_of_inertia
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Tangential part of the ball-ball contact law (rough-disk/sphere model).
+///
+/// The tangential impulse aims to reverse the tangential contact velocity
+/// `g_t` to `-β g_t`, but is capped at `μ` times the normal impulse; when the
+/// cap applies the balls slide.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Friction {
+    /// Tangential restitution β in `[-1, 1]`: -1 is smooth, 1 perfectly rough.
+    pub tangential_restitution: f32,
+    /// Coulomb friction coefficient μ.
+    pub coefficient: f32,
+}
+
+impl Friction {
+    /// Frictionless contacts: no spin is ever exchanged.
+    pub const SMOOTH: Friction = Friction {
+        tangential_restitution: -1.0,
+        coefficient: 0.0,
+    };
+
+    /// Perfectly rough, non-sliding contacts; conserve total kinetic energy.
+    pub const ROUGH: Friction = Friction {
+        tangential_restitution: 1.0,
+        coefficient: f32::INFINITY,
+    };
+}
+
+impl Default for Friction {
+    fn default() -> Self {
+        Friction::SMOOTH
+    }
+}
+
+/// How `World::step` finds ball pairs to test for contact.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BroadPhase {
+    /// Test every pair: O(n²), kept as the reference implementation.
+    BruteForce,
+    /// Bin balls into a uniform grid rebuilt every step and only test
+    /// neighbouring cells.
+    #[default]
+    CellList,
+}
+
+/// One of the walls of the box: `Left`/`Right` at `x = 0` and `x = size.x`,
+/// `Top`/`Bottom` at `y = 0` and `y = size.y`, and in 3D `Front`/`Back` at
+/// `z = 0` and `z = size.z`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Front,
+    Back,
+}
+
+impl Side {
+    pub const ALL: [Side; 6] = [
+        Side::Left,
+        Side::Right,
+        Side::Top,
+        Side::Bottom,
+        Side::Front,
+        Side::Back,
+    ];
+
+    /// Coordinate axis the wall is perpendicular to.
+    pub fn axis(self) -> usize {
+        self as usize / 2
+    }
+
+    /// Sign of the inward wall normal along `axis`.
+    pub fn inward(self) -> f32 {
+        if (self as usize).is_multiple_of(2) {
+            1.0
+        } else {
+            -1.0
+        }
+    }
+}
+
+/// What a wall does to a ball that hits it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Wall {
+    /// Mirror reflection of the normal velocity.
+    #[default]
+    Specular,
+    /// Diffuse re-emission from a reservoir at temperature `kT` (in units of
+    /// mass × velocity²): the outgoing normal speed follows the Maxwell flux
+    /// distribution `v exp(-m v² / 2kT)` and each tangential velocity
+    /// component a Gaussian of variance `kT / m`. Spin is left untouched.
+    Thermal { temperature: f32 },
 }
 
+/// Balls in an axis-aligned box from the origin to `size`: a rectangle of
+/// disks for `V = Vec2`, a box of spheres for `V = Vec3`.
 #[derive(Debug, Clone)]
-pub struct World {
-    pub width: f32,
-    pub height: f32,
-    pub balls: Vec<Ball>,
+pub struct World<V: Vector = Vec2> {
+    pub size: V,
+    pub balls: Vec<Ball<V>>,
+    pub friction: Friction,
+    /// Wall types, indexed by `Side as usize`. `Front` and `Back` only
+    /// matter in 3D.
+    pub walls: [Wall; 6],
+    /// Energy each wall has put into the balls (negative when it absorbed
+    /// energy), indexed by `Side as usize`. Only thermal walls exchange heat.
+    pub heat: [f32; 6],
+    rng: StdRng,
+    /// Simulated time since the world was created.
+    time: f64,
+    pressure_window: PressureWindow,
+    rdf: Option<RadialDistribution>,
+    collision_log: Option<CollisionLog<V>>,
+}
+
+impl World<Vec2> {
+    /// A `width × height` rectangle; see `World::with_size`.
+    pub fn new(width: f32, height: f32, balls: Vec<Ball>) -> Self {
+        Self::with_size(Vec2::new(width, height), balls)
+    }
 }
 
-impl World {
+impl<V: Vector> World<V> {
+    /// A box with smooth (frictionless) ball-ball contacts and specular
+    /// walls.
+    pub fn with_size(size: V, balls: Vec<Ball<V>>) -> Self {
+        Self {
+            size,
+            balls,
+            friction: Friction::default(),
+            walls: [Wall::Specular; 6],
+            heat: [0.0; 6],
+            rng: StdRng::seed_from_u64(0),
+            time: 0.0,
+            pressure_window: PressureWindow::default(),
+            rdf: None,
+            collision_log: None,
+        }
+    }
+
+    /// The walls this world actually has: four in 2D, six in 3D.
+    pub fn sides() -> &'static [Side] {
+        &Side::ALL[..2 * V::DIM]
+    }
+
+    pub fn set_wall(&mut self, side: Side, wall: Wall) {
+        self.walls[side as usize] = wall;
+    }
+
+    /// Energy the wall on `side` has put into the balls so far.
+    pub fn heat_exchanged(&self, side: Side) -> f32 {
+        self.heat[side as usize]
+    }
+
+    /// Simulated time since the world was created.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
+    /// Restart the random stream used by thermal walls.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
     pub fn step(&mut self, dt: f32) {
+        self.step_with(dt, BroadPhase::default());
+    }
+
+    /// Like `step`, but with an explicit broad phase. Both broad phases
+    /// resolve contacts in index order, so they agree whenever the contacts
+    /// of a step are independent. The cell list collects its candidate pairs
+    /// before any position correction, though, so when a correction pushes a
+    /// ball into a new overlap the brute-force pass resolves it in the same
+    /// step while the cell list leaves it for the next.
+    pub fn step_with(&mut self, dt: f32, broad_phase: BroadPhase) {
+        self.time += f64::from(dt);
         for ball in &mut self.balls {
             ball.position += ball.velocity * dt;
+            ball.orientation = V::rotate(ball.orientation, ball.angular_velocity, dt);
+
+            for &side in Self::sides() {
+                let axis = side.axis();
+                let inward = side.inward();
+                let wall = if inward > 0.0 { 0.0 } else { self.size[axis] };
+                if (ball.position[axis] - wall) * inward >= ball.radius {
+                    continue;
+                }
+                ball.position[axis] = wall + inward * ball.radius;
 
-            if ball.position.x - ball.radius < 0.0 {
-                ball.position.x = ball.radius;
-                ball.velocity.x = ball.velocity.x.abs();
+                match self.walls[side as usize] {
+                    Wall::Specular => ball.velocity[axis] = inward * ball.velocity[axis].abs(),
+                    Wall::Thermal { temperature } => {
+                        // A ball pushed into the wall by a contact but already
+                        // leaving it never reached the reservoir
+                        if ball.velocity[axis] * inward > 0.0 {
+                            continue;
+                        }
+                        let before = ball.velocity.length_squared();
+                        let sigma = (temperature / ball.mass).sqrt();
+                        let u: f32 = self.rng.r#gen();
+                        ball.velocity[axis] = inward * sigma * (-2.0 * (1.0 - u).ln()).sqrt();
+                        for k in (0..V::DIM).filter(|&k| k != axis) {
+                            ball.velocity[k] = sigma * gaussian(&mut self.rng);
+                        }
+                        self.heat[side as usize] +=
+                            0.5 * ball.mass * (ball.velocity.length_squared() - before);
+                    }
+                }
             }
-            if ball.position.x + ball.radius > self.width {
-                ball.position.x = self.width - ball.radius;
-                ball.velocity.x = -ball.velocity.x.abs();
+        }
+
+        if let Some(log) = &mut self.collision_log {
+            log.advance(dt, self.balls.iter().map(|b| b.velocity));
+        }
+
+        // On steps sampled for g(r), the contact search doubles as the pair
+        // loop for the histogram, with cells widened to its range
+        let sampling = self.rdf.as_mut().is_some_and(|rdf| rdf.due());
+        let reach = match &self.rdf {
+            Some(rdf) if sampling => rdf.range(),
+            _ => 0.0,
+        };
+        match broad_phase {
+            BroadPhase::BruteForce => {
+                let len = self.balls.len();
+                for i in 0..len {
+                    for j in (i + 1)..len {
+                        self.visit_pair(i, j, sampling);
+                    }
+                }
             }
-            if ball.position.y - ball.radius < 0.0 {
-                ball.position.y = ball.radius;
-                ball.velocity.y = ball.velocity.y.abs();
+            BroadPhase::CellList => {
+                for (i, j) in cell_list::candidate_pairs(&self.balls, self.size, reach) {
+                    self.visit_pair(i, j, sampling);
+                }
             }
-            if ball.position.y + ball.radius > self.height {
-                ball.position.y = self.height - ball.radius;
-                ball.velocity.y = -ball.velocity.y.abs();
+        }
+        if sampling && let Some(rdf) = &mut self.rdf {
+            let positions: Vec<V> = self.balls.iter().map(|b| b.position).collect();
+            rdf.add_frame(&positions, self.size);
+        }
+
+        let kinetic = 2.0 * self.translational_energy() / V::DIM as f32;
+        self.pressure_window.duration += dt;
+        self.pressure_window.kinetic += kinetic * dt;
+    }
+
+    /// Pairs `(i, j)` with `i < j` that currently overlap or touch.
+    pub fn contacts(&self, broad_phase: BroadPhase) -> Vec<(usize, usize)> {
+        let touching = |&(i, j): &(usize, usize)| {
+            let (a, b) = (&self.balls[i], &self.balls[j]);
+            let min_dist = a.radius + b.radius;
+            (b.position - a.position).length_squared() <= min_dist * min_dist
+        };
+
+        match broad_phase {
+            BroadPhase::BruteForce => {
+                let len = self.balls.len();
+                (0..len)
+                    .flat_map(|i| ((i + 1)..len).map(move |j| (i, j)))
+                    .filter(touching)
+                    .collect()
             }
+            BroadPhase::CellList => cell_list::candidate_pairs(&self.balls, self.size, 0.0)
+                .into_iter()
+                .filter(touching)
+                .collect(),
         }
+    }
 
-        let len = self.balls.len();
-        for i in 0..len {
-            for j in (i + 1)..len {
-                let (left, right) = self.balls.split_at_mut(j);
-                let a = &mut left[i];
-                let b = &mut right[0];
+    fn visit_pair(&mut self, i: usize, j: usize, sampling: bool) {
+        if sampling && let Some(rdf) = &mut self.rdf {
+            let delta = self.balls[j].position - self.balls[i].position;
+            rdf.add_pair(delta.length_squared().sqrt());
+        }
+        self.resolve_pair(i, j);
+    }
 
-                let delta = b.position - a.position;
-                let min_dist = a.radius + b.radius;
-                let dist_sq = delta.length_squared();
-                if dist_sq > min_dist * min_dist {
-                    continue;
-                }
+    fn resolve_pair(&mut self, i: usize, j: usize) {
+        let friction = self.friction;
+        let (left, right) = self.balls.split_at_mut(j);
+        let a = &mut left[i];
+        let b = &mut right[0];
 
-                let normal = if dist_sq > 1e-12 {
-                    delta / dist_sq.sqrt()
-                } else {
-                    let rv = b.velocity - a.velocity;
-                    if rv.length_squared() > 1e-12 {
-                        rv.normalize()
-                    } else {
-                        Vec2::X
-                    }
-                };
+        let delta = b.position - a.position;
+        let min_dist = a.radius + b.radius;
+        let dist_sq = delta.length_squared();
+        if dist_sq > min_dist * min_dist {
+            return;
+        }
 
-                let rv = b.velocity - a.velocity;
-                let vel_along_normal = rv.dot(normal);
+        let normal = if dist_sq > 1e-12 {
+            delta / dist_sq.sqrt()
+        } else {
+            let rv = b.velocity - a.velocity;
+            if rv.length_squared() > 1e-12 {
+                rv / rv.length_squared().sqrt()
+            } else {
+                V::X
+            }
+        };
 
-                if vel_along_normal < 0.0 {
-                    let inv_mass_a = 1.0 / a.mass;
-                    let inv_mass_b = 1.0 / b.mass;
-                    let impulse_mag = -(1.0 + 1.0) * vel_along_normal / (inv_mass_a + inv_mass_b);
-                    let impulse = impulse_mag * normal;
+        let rv = b.velocity - a.velocity;
+        let vel_along_normal = rv.dot(normal);
 
-                    a.velocity -= impulse * inv_mass_a;
-                    b.velocity += impulse * inv_mass_b;
-                }
+        if vel_along_normal < 0.0 {
+            let inv_mass_a = 1.0 / a.mass;
+            let inv_mass_b = 1.0 / b.mass;
+            let impulse_mag = -(1.0 + 1.0) * vel_along_normal / (inv_mass_a + inv_mass_b);
+            let impulse = normal * impulse_mag;
+            let mut total_impulse = impulse;
 
-                let dist = dist_sq.sqrt();
-                let penetration = (min_dist - dist).max(0.0);
-                if penetration > 0.0 {
-                    let inv_mass_a = 1.0 / a.mass;
-                    let inv_mass_b = 1.0 / b.mass;
-                    let total_inv_mass = inv_mass_a + inv_mass_b;
-                    if total_inv_mass > 0.0 {
-                        let correction = normal * (penetration / total_inv_mass);
-                        a.position -= correction * inv_mass_a;
-                        b.position += correction * inv_mass_b;
-                    }
-                }
+            a.velocity -= impulse * inv_mass_a;
+            b.velocity += impulse * inv_mass_b;
+            // Tangential impulses are perpendicular to `delta` and add nothing
+            self.pressure_window.collisional += dist_sq.sqrt() * impulse_mag;
+
+            // Tangential impulse from the slip velocity of the contact points
+            // (the normal impulse does not change it).
+            let spin = a.angular_velocity * a.radius + b.angular_velocity * b.radius;
+            let contact_velocity = rv - V::spin_cross(spin, normal);
+            let slip_velocity = contact_velocity - normal * contact_velocity.dot(normal);
+            let slip = slip_velocity.length_squared().sqrt();
+            let inv_mass_t = inv_mass_a
+                + inv_mass_b
+                + a.radius * a.radius * a.inverse_inertia()
+                + b.radius * b.radius * b.inverse_inertia();
+            let sticking = -(1.0 + friction.tangential_restitution) * slip / inv_mass_t;
+            let limit = friction.coefficient * impulse_mag;
+            let tangential = sticking.clamp(-limit, limit);
+
+            if tangential != 0.0 && slip > 0.0 {
+                // Impulse on `b`; the contact point sits at -r_b n from its
+                // centre and at +r_a n from `a`'s
+                let impulse_t = slip_velocity * (tangential / slip);
+                let torque = normal.cross(impulse_t);
+                a.velocity -= impulse_t * inv_mass_a;
+                b.velocity += impulse_t * inv_mass_b;
+                a.angular_velocity -= torque * (a.radius * a.inverse_inertia());
+                b.angular_velocity -= torque * (b.radius * b.inverse_inertia());
+                total_impulse += impulse_t;
+            }
+
+            if let Some(log) = &mut self.collision_log {
+                let overlap = min_dist - dist_sq.sqrt();
+                log.record(Collision {
+                    time: self.time,
+                    i,
+                    j,
+                    point: a.position + normal * (a.radius - 0.5 * overlap),
+                    normal,
+                    impulse: total_impulse,
+                    relative_speed: rv.length_squared().sqrt(),
+                });
+            }
+        }
+
+        let dist = dist_sq.sqrt();
+        let penetration = (min_dist - dist).max(0.0);
+        if penetration > 0.0 {
+            let inv_mass_a = 1.0 / a.mass;
+            let inv_mass_b = 1.0 / b.mass;
+            let total_inv_mass = inv_mass_a + inv_mass_b;
+            if total_inv_mass > 0.0 {
+                let correction = normal * (penetration / total_inv_mass);
+                a.position -= correction * inv_mass_a;
+                b.position += correction * inv_mass_b;
             }
         }
     }
 }
 
+/// Standard normal deviate (Box–Muller).
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u: f32 = rng.r#gen();
+    let v: f32 = rng.r#gen();
+    (-2.0 * (1.0 - u).ln()).sqrt() * (TAU * v).cos()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use glam::Vec3;
 
     const EPS: f32 = 1e-4;
+    const BROAD_PHASES: [BroadPhase; 2] = [BroadPhase::BruteForce, BroadPhase::CellList];
 
-    fn kinetic_energy(world: &World) -> f32 {
-        world
-            .balls
-            .iter()
-            .map(|b| 0.5 * b.mass * b.velocity.length_squared())
-            .sum()
+    #[test]
+    fn reflects_on_vertical_wall() {
+        for broad_phase in BROAD_PHASES {
+            let mut world = World::new(
+                10.0,
+                10.0,
+                vec![Ball::new(
+                    Vec2::new(9.0, 5.0),
+                    Vec2::new(3.0, 0.0),
+                    1.0,
+                    1.0,
+                )],
+            );
+
+            world.step_with(1.0, broad_phase);
+
+            assert!((world.balls[0].position.x - 9.0).abs() < EPS);
+            assert!((world.balls[0].velocity.x + 3.0).abs() < EPS);
+        }
     }
 
-    fn momentum(world: &World) -> Vec2 {
-        world
-            .balls
-            .iter()
-            .map(|b| b.mass * b.velocity)
-            .fold(Vec2::ZERO, |acc, x| acc + x)
+    #[test]
+    fn head_on_equal_mass_collision_swaps_velocities() {
+        for broad_phase in BROAD_PHASES {
+            let mut world = World::new(
+                30.0,
+                10.0,
+                vec![
+                    Ball::new(Vec2::new(10.0, 5.0), Vec2::new(1.0, 0.0), 1.0, 1.0),
+                    Ball::new(Vec2::new(12.0, 5.0), Vec2::new(-1.0, 0.0), 1.0, 1.0),
+                ],
+            );
+
+            world.step_with(0.0, broad_phase);
+
+            assert!((world.balls[0].velocity.x + 1.0).abs() < EPS);
+            assert!((world.balls[1].velocity.x - 1.0).abs() < EPS);
+        }
     }
 
     #[test]
-    fn reflects_on_vertical_wall() {
-        let mut world = World {
-            width: 10.0,
-            height: 10.0,
-            balls: vec![Ball {
-                position: Vec2::new(9.0, 5.0),
-                velocity: Vec2::new(3.0, 0.0),
-                radius: 1.0,
-                mass: 1.0,
-            }],
-        };
+    fn conserves_total_momentum_and_energy_for_ball_collision() {
+        for broad_phase in BROAD_PHASES {
+            let mut world = World::new(
+                40.0,
+                40.0,
+                vec![
+                    Ball::new(Vec2::new(10.0, 10.0), Vec2::new(3.0, 1.5), 1.0, 2.0),
+                    Ball::new(Vec2::new(11.8, 10.0), Vec2::new(-0.5, 0.2), 1.0, 1.0),
+                ],
+            );
+
+            let before_p = world.momentum();
+            let before_e = world.translational_energy();
+
+            world.step_with(0.0, broad_phase);
+
+            let after_p = world.momentum();
+            let after_e = world.translational_energy();
+
+            assert!((before_p.x - after_p.x).abs() < 1e-3);
+            assert!((before_p.y - after_p.y).abs() < 1e-3);
+            assert!((before_e - after_e).abs() < 1e-3);
+        }
+    }
+
+    /// Uniform deviates in `[0, 1]` from a 32-bit xorshift generator.
+    fn xorshift(mut seed: u32) -> impl FnMut() -> f32 {
+        move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 17;
+            seed ^= seed << 5;
+            seed as f32 / u32::MAX as f32
+        }
+    }
 
-        world.step(1.0);
+    /// Deterministic dense gas: a jittered lattice with some overlaps.
+    fn dense_world(count: usize) -> World {
+        let mut next = xorshift(0x2545_f491);
 
-        assert!((world.balls[0].position.x - 9.0).abs() < EPS);
-        assert!((world.balls[0].velocity.x + 3.0).abs() < EPS);
+        let cols = (count as f32).sqrt().ceil() as usize;
+        let balls = (0..count)
+            .map(|k| {
+                let radius = 0.5 + 0.5 * next();
+                let position = Vec2::new(
+                    1.5 + 1.8 * (k % cols) as f32 + 0.6 * next(),
+                    1.5 + 1.8 * (k / cols) as f32 + 0.6 * next(),
+                );
+                let velocity = Vec2::new(next() - 0.5, next() - 0.5) * 20.0;
+                Ball::new(position, velocity, radius, radius * radius)
+            })
+            .collect();
+
+        World::new(2.0 + 1.8 * cols as f32, 2.0 + 1.8 * cols as f32, balls)
     }
 
     #[test]
-    fn head_on_equal_mass_collision_swaps_velocities() {
-        let mut world = World {
-            width: 30.0,
-            height: 10.0,
-            balls: vec![
-                Ball {
-                    position: Vec2::new(10.0, 5.0),
-                    velocity: Vec2::new(1.0, 0.0),
-                    radius: 1.0,
-                    mass: 1.0,
-                },
-                Ball {
-                    position: Vec2::new(12.0, 5.0),
-                    velocity: Vec2::new(-1.0, 0.0),
-                    radius: 1.0,
-                    mass: 1.0,
-                },
+    fn cell_list_finds_same_contacts_as_brute_force() {
+        let mut world = dense_world(400);
+
+        for _ in 0..50 {
+            let brute = world.contacts(BroadPhase::BruteForce);
+            let grid = world.contacts(BroadPhase::CellList);
+            assert!(!brute.is_empty());
+            assert_eq!(brute, grid);
+
+            world.step_with(0.01, BroadPhase::BruteForce);
+        }
+    }
+
+    #[test]
+    fn cell_list_contacts_match_along_cell_list_run() {
+        // Trajectories may part ways once a position correction creates an
+        // overlap, so compare what each broad phase sees at every step
+        let mut world = dense_world(200);
+
+        for _ in 0..200 {
+            assert_eq!(
+                world.contacts(BroadPhase::BruteForce),
+                world.contacts(BroadPhase::CellList)
+            );
+            world.step_with(0.01, BroadPhase::CellList);
+        }
+    }
+
+    /// Two touching balls (normal along y) meeting at a glancing angle.
+    fn glancing_pair(friction: Friction) -> World {
+        let mut world = World::new(
+            40.0,
+            40.0,
+            vec![
+                Ball::new(Vec2::new(10.0, 10.0), Vec2::new(2.0, 1.0), 1.0, 2.0),
+                Ball::new(Vec2::new(10.0, 12.0), Vec2::new(-1.0, 0.0), 1.0, 1.0),
             ],
-        };
+        );
+        world.friction = friction;
+        world
+    }
+
+    #[test]
+    fn rough_collision_converts_translation_into_spin() {
+        let mut world = glancing_pair(Friction::ROUGH);
+        let before_p = world.momentum();
+        let before_e = world.kinetic_energy();
+        let before_l = world.angular_momentum();
+        let before_translational = world.translational_energy();
 
         world.step(0.0);
 
-        assert!((world.balls[0].velocity.x + 1.0).abs() < EPS);
-        assert!((world.balls[1].velocity.x - 1.0).abs() < EPS);
+        assert!(world.balls.iter().all(|b| b.angular_velocity.abs() > 0.1));
+        assert!(world.translational_energy() < before_translational - 0.1);
+        assert!((world.momentum() - before_p).length() < 1e-4);
+        assert!((world.kinetic_energy() - before_e).abs() < 1e-4);
+        assert!((world.angular_momentum() - before_l).abs() < 1e-3);
     }
 
     #[test]
-    fn conserves_total_momentum_and_energy_for_ball_collision() {
-        let mut world = World {
-            width: 40.0,
-            height: 40.0,
-            balls: vec![
-                Ball {
-                    position: Vec2::new(10.0, 10.0),
-                    velocity: Vec2::new(3.0, 1.5),
-                    radius: 1.0,
-                    mass: 2.0,
-                },
-                Ball {
-                    position: Vec2::new(11.8, 10.0),
-                    velocity: Vec2::new(-0.5, 0.2),
-                    radius: 1.0,
-                    mass: 1.0,
-                },
+    fn coulomb_friction_caps_tangential_impulse() {
+        let mu = 0.1;
+        let mut world = glancing_pair(Friction {
+            tangential_restitution: 1.0,
+            coefficient: mu,
+        });
+        let before_v = world.balls[1].velocity;
+        let before_e = world.kinetic_energy();
+
+        world.step(0.0);
+
+        let impulse = (world.balls[1].velocity - before_v) * world.balls[1].mass;
+        assert!(impulse.y > 0.0);
+        assert!((impulse.x.abs() - mu * impulse.y).abs() < EPS);
+        assert!(world.kinetic_energy() < before_e);
+    }
+
+    #[test]
+    fn smooth_collision_leaves_spin_untouched() {
+        let mut world = glancing_pair(Friction::SMOOTH);
+        world.balls[0].angular_velocity = 3.0;
+
+        world.step(0.0);
+
+        assert_eq!(world.balls[0].angular_velocity, 3.0);
+        assert_eq!(world.balls[1].angular_velocity, 0.0);
+    }
+
+    #[test]
+    fn thermal_walls_bring_gas_to_wall_temperature() {
+        let temperature = 4.0;
+        let mut world = dense_world(100);
+        world.walls = [Wall::Thermal { temperature }; 6];
+
+        for _ in 0..3000 {
+            world.step(0.01);
+        }
+
+        let mut sum = 0.0;
+        let samples = 3000;
+        for _ in 0..samples {
+            world.step(0.01);
+            sum += world.temperature();
+        }
+        let measured = sum / samples as f32;
+        assert!(
+            (measured - temperature).abs() < 0.1 * temperature,
+            "kT = {measured}"
+        );
+    }
+
+    #[test]
+    fn heat_flows_from_hot_to_cold_wall() {
+        let mut world = dense_world(100);
+        world.set_wall(Side::Left, Wall::Thermal { temperature: 8.0 });
+        world.set_wall(Side::Right, Wall::Thermal { temperature: 2.0 });
+
+        for _ in 0..3000 {
+            world.step(0.01);
+        }
+        world.heat = [0.0; 6];
+        let before = world.translational_energy();
+        for _ in 0..10000 {
+            world.step(0.01);
+        }
+
+        let hot = world.heat_exchanged(Side::Left);
+        let cold = world.heat_exchanged(Side::Right);
+        assert!(hot > 0.0 && cold < 0.0);
+        assert_eq!(world.heat_exchanged(Side::Top), 0.0);
+        // Energy bookkeeping, and in the steady state what goes in comes out
+        assert!((world.translational_energy() - before - hot - cold).abs() < 1e-3 * hot);
+        assert!((hot + cold).abs() < 0.1 * hot, "in {hot}, out {cold}");
+    }
+
+    /// Deterministic dense hard-sphere gas on a jittered cubic lattice.
+    fn dense_world_3d(count: usize) -> World<Vec3> {
+        let mut next = xorshift(0x9e37_79b9);
+
+        let side = (count as f32).cbrt().ceil() as usize;
+        let balls = (0..count)
+            .map(|k| {
+                let radius = 0.5 + 0.5 * next();
+                let lattice = Vec3::new(
+                    (k % side) as f32,
+                    (k / side % side) as f32,
+                    (k / (side * side)) as f32,
+                );
+                let jitter = Vec3::new(next(), next(), next());
+                let velocity = Vec3::new(next() - 0.5, next() - 0.5, next() - 0.5) * 20.0;
+                Ball::new(
+                    Vec3::splat(1.5) + lattice * 1.8 + jitter * 0.6,
+                    velocity,
+                    radius,
+                    radius.powi(3),
+                )
+            })
+            .collect();
+
+        World::with_size(Vec3::splat(2.0 + 1.8 * side as f32), balls)
+    }
+
+    #[test]
+    fn hard_sphere_cell_list_matches_brute_force() {
+        let mut world = dense_world_3d(343);
+        let before = world.translational_energy();
+
+        for _ in 0..100 {
+            let brute = world.contacts(BroadPhase::BruteForce);
+            assert!(!brute.is_empty());
+            assert_eq!(brute, world.contacts(BroadPhase::CellList));
+            world.step(0.01);
+        }
+
+        assert!((world.translational_energy() - before).abs() < 1e-3 * before);
+    }
+
+    #[test]
+    fn rough_spheres_conserve_energy_and_angular_momentum() {
+        let mut world = World::with_size(
+            Vec3::splat(40.0),
+            vec![
+                Ball::new(
+                    Vec3::new(10.0, 10.0, 10.0),
+                    Vec3::new(2.0, 1.0, -0.5),
+                    1.0,
+                    2.0,
+                ),
+                Ball::new(
+                    Vec3::new(10.0, 12.0, 10.0),
+                    Vec3::new(-1.0, 0.0, 1.0),
+                    1.0,
+                    1.0,
+                ),
             ],
-        };
+        );
+        world.friction = Friction::ROUGH;
+        world.balls[0].angular_velocity = Vec3::new(0.0, 0.0, 1.5);
 
-        let before_p = momentum(&world);
-        let before_e = kinetic_energy(&world);
+        let before_e = world.kinetic_energy();
+        let before_l = world.angular_momentum();
 
         world.step(0.0);
 
-        let after_p = momentum(&world);
-        let after_e = kinetic_energy(&world);
+        assert!(world.balls[1].angular_velocity.length() > 0.1);
+        assert!((world.kinetic_energy() - before_e).abs() < 1e-4);
+        assert!((world.angular_momentum() - before_l).length() < 1e-3);
+    }
+
+    /// Panics if any disk pokes through a wall or overlaps another by more
+    /// than a small fraction of its radius.
+    fn assert_disjoint(width: f32, height: f32, radii: &[f32], positions: &[Vec2]) {
+        const TOLERANCE: f32 = 1e-4;
+        assert_eq!(radii.len(), positions.len());
+        for (i, (&r, &p)) in radii.iter().zip(positions).enumerate() {
+            let slack = TOLERANCE * r;
+            assert!(
+                p.x >= r - slack && p.x <= width - r + slack,
+                "disk {i} at {p}"
+            );
+            assert!(
+                p.y >= r - slack && p.y <= height - r + slack,
+                "disk {i} at {p}"
+            );
+            for j in (i + 1)..radii.len() {
+                let gap = p.distance(positions[j]) - (r + radii[j]);
+                assert!(gap >= -TOLERANCE * r, "disks {i} and {j} overlap by {gap}");
+            }
+        }
+    }
+
+    #[test]
+    fn random_sequential_addition_places_disjoint_disks() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let radii: Vec<f32> = (0..100).map(|k| 0.5 + 0.005 * k as f32).collect();
+
+        let positions = placement::random_sequential(30.0, 30.0, &radii, 1000, &mut rng).unwrap();
+        assert_disjoint(30.0, 30.0, &radii, &positions);
+    }
+
+    #[test]
+    fn lattices_fill_box_or_report_capacity() {
+        // Exactly 10 × 10 touching disks
+        let (width, height) = (21.0, 2.0 + 18.0 * 0.75_f32.sqrt());
+        let hex = placement::hexagonal_lattice(width, height, 1.0, 100).unwrap();
+        assert_disjoint(width, height, &[1.0; 100], &hex);
+        assert!(placement::area_fraction(width, height, &[1.0; 100]) > 0.85);
+
+        let square = placement::square_lattice(20.0, 20.0, 1.0, 100).unwrap();
+        assert_disjoint(20.0, 20.0, &[1.0; 100], &square);
+
+        assert_eq!(
+            placement::square_lattice(20.0, 20.0, 1.0, 101),
+            Err(placement::PlacementError::NoRoom { placed: 100 })
+        );
+        assert_eq!(
+            placement::hexagonal_lattice(width, height, 1.01, 100),
+            Err(placement::PlacementError::NoRoom { placed: 81 })
+        );
+    }
+
+    #[test]
+    fn compression_reaches_target_area_fraction() {
+        let mut rng = StdRng::seed_from_u64(2);
+        // 50:50 mixture with size ratio 1.4, which does not crystallise
+        let sizes: Vec<f32> = (0..64)
+            .map(|k| if k % 2 == 0 { 1.0 } else { 1.4 })
+            .collect();
+        let radii = placement::radii_for_fraction(20.0, 20.0, &sizes, 0.75);
+
+        let positions = placement::compress(20.0, 20.0, &radii, &mut rng).unwrap();
+        assert!((placement::area_fraction(20.0, 20.0, &radii) - 0.75).abs() < 1e-4);
+        assert_disjoint(20.0, 20.0, &radii, &positions);
+    }
+
+    #[test]
+    fn infeasible_packings_are_rejected() {
+        use placement::PlacementError;
+        let mut rng = StdRng::seed_from_u64(3);
+
+        let radii = placement::radii_for_fraction(20.0, 20.0, &[1.0; 50], 0.95);
+        assert!(matches!(
+            placement::compress(20.0, 20.0, &radii, &mut rng),
+            Err(PlacementError::TooDense { .. })
+        ));
+
+        // Well beyond the random sequential addition limit
+        let radii = placement::radii_for_fraction(20.0, 20.0, &[1.0; 50], 0.7);
+        assert!(matches!(
+            placement::random_sequential(20.0, 20.0, &radii, 1000, &mut rng),
+            Err(PlacementError::NoRoom { .. })
+        ));
+
+        assert_eq!(
+            placement::random_sequential(20.0, 20.0, &[1.0, 11.0], 10, &mut rng),
+            Err(PlacementError::DiskTooLarge { index: 1 })
+        );
+    }
+
+    /// `count` unit disks of unit mass at area fraction `fraction` in a
+    /// square box, placed by random sequential addition with Gaussian
+    /// velocity components of standard deviation `speed`.
+    fn hard_disk_gas(count: usize, fraction: f32, speed: f32, seed: u64) -> World {
+        let side = (count as f32 * std::f32::consts::PI / fraction).sqrt();
+        let mut rng = StdRng::seed_from_u64(seed);
+        let positions =
+            placement::random_sequential(side, side, &vec![1.0; count], 1000, &mut rng).unwrap();
+        let balls = positions
+            .into_iter()
+            .map(|p| {
+                let v = Vec2::new(gaussian(&mut rng), gaussian(&mut rng)) * speed;
+                Ball::new(p, v, 1.0, 1.0)
+            })
+            .collect();
+        World::new(side, side, balls)
+    }
+
+    #[test]
+    fn virial_pressure_matches_hard_disk_equation_of_state() {
+        let fraction = 0.3;
+        let mut world = hard_disk_gas(200, fraction, 1.0, 4);
+
+        for _ in 0..1000 {
+            world.step(0.005);
+        }
+        world.reset_pressure();
+        let mut kt = 0.0;
+        for _ in 0..6000 {
+            world.step(0.005);
+            kt += world.temperature() / 6000.0;
+        }
+
+        // Henderson: P A / (N kT) = (1 + φ²/8) / (1 - φ)²
+        let henderson = (1.0 + fraction * fraction / 8.0) / (1.0 - fraction).powi(2);
+        let measured = world.pressure() * world.volume() / (200.0 * kt);
+        assert!(
+            (measured - henderson).abs() < 0.05 * henderson,
+            "Z = {measured}"
+        );
+        assert!((world.pressure_window().duration - 30.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn ideal_gas_rdf_is_flat_up_to_the_walls() {
+        // Near-point balls barely collide; shells up to 20 reach well past
+        // the walls of the 50 × 30 box
+        let mut rng = StdRng::seed_from_u64(5);
+        let balls = (0..300)
+            .map(|_| {
+                let p = Vec2::new(rng.gen_range(0.0..50.0), rng.gen_range(0.0..30.0));
+                let v = Vec2::new(gaussian(&mut rng), gaussian(&mut rng)) * 10.0;
+                Ball::new(p, v, 1e-3, 1.0)
+            })
+            .collect();
+        let mut brute = World::new(50.0, 30.0, balls);
+        brute.record_rdf(RadialDistribution::new(20.0, 20, 5));
+        let mut grid = brute.clone();
+
+        for _ in 0..1000 {
+            brute.step_with(0.01, BroadPhase::BruteForce);
+            grid.step_with(0.01, BroadPhase::CellList);
+        }
+
+        let rdf = grid.take_rdf().unwrap();
+        assert_eq!(Some(&rdf), brute.rdf());
+        assert_eq!(rdf.frames(), 200);
+        for (r, g) in rdf.values() {
+            assert!((g - 1.0).abs() < 0.05, "g({r}) = {g}");
+        }
+
+        let mut out = Vec::new();
+        rdf.write(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        assert_eq!(text.lines().count(), 21);
+        assert!(text.lines().skip(1).all(|l| l.split(' ').count() == 2));
+    }
+
+    #[test]
+    fn hard_disk_rdf_peaks_at_contact() {
+        let mut world = hard_disk_gas(200, 0.3, 1.0, 6);
+
+        for _ in 0..1000 {
+            world.step(0.005);
+        }
+        world.record_rdf(RadialDistribution::new(6.0, 60, 10));
+        for _ in 0..6000 {
+            world.step(0.005);
+        }
+
+        // Hardly any pairs inside the core, bar the slight overlaps a fixed
+        // step allows before a collision is resolved; the contact value from
+        // the Henderson equation of state is (1 - 7φ/16) / (1 - φ)² ≈ 1.77
+        let values = world.rdf().unwrap().values();
+        let (core, shell): (Vec<_>, Vec<_>) = values.iter().partition(|(r, _)| *r < 2.0);
+        assert!(core.iter().all(|&&(_, g)| g < 0.1), "{core:?}");
+        let contact = shell[0].1;
+        assert!((1.5..2.0).contains(&contact), "g(2) = {contact}");
+        assert!(shell.iter().all(|&&(_, g)| g <= contact + 0.1));
+        let tail = &shell[shell.len() - 10..];
+        assert!(
+            tail.iter().all(|&&(_, g)| (g - 1.0).abs() < 0.15),
+            "{tail:?}"
+        );
+    }
+
+    #[test]
+    fn collision_log_records_head_on_collision() {
+        for broad_phase in BROAD_PHASES {
+            // Overlapping by 0.2, the right ball three times as heavy
+            let mut world = World::new(
+                30.0,
+                10.0,
+                vec![
+                    Ball::new(Vec2::new(10.0, 5.0), Vec2::new(2.0, 0.0), 1.0, 1.0),
+                    Ball::new(Vec2::new(11.8, 5.0), Vec2::new(-1.0, 0.0), 1.0, 3.0),
+                ],
+            );
+            world.record_collisions(CollisionLog::with_events());
+
+            world.step_with(0.0, broad_phase);
+            world.step_with(0.5, broad_phase);
+
+            let log = world.collision_log_mut().unwrap();
+            let events = log.drain_events();
+            assert_eq!(events.len(), 1);
+            let event = events[0];
+            assert_eq!((event.time, event.i, event.j), (0.0, 0, 1));
+            assert!((event.point - Vec2::new(10.9, 5.0)).length() < EPS);
+            assert!((event.normal - Vec2::X).length() < EPS);
+            // 2 μ v with μ = 3/4
+            assert!((event.impulse - Vec2::new(4.5, 0.0)).length() < EPS);
+            assert!((event.relative_speed - 3.0).abs() < EPS);
+            assert!(log.events().is_empty());
+
+            assert_eq!(log.total(), 1);
+            assert_eq!(log.counts(), &[1, 1]);
+            assert_eq!(log.duration(), 0.5);
+            // Speeds 2.5 and 0.5 after the collision
+            assert!((log.mean_free_path() - 0.75).abs() < 1e-6);
+            assert!((log.mean_free_time() - 0.5).abs() < 1e-6);
+            assert_eq!(world.time(), 0.5);
+        }
+    }
+
+    #[test]
+    fn collision_log_is_infinite_before_first_collision() {
+        let log = CollisionLog::<Vec2>::new();
+        assert_eq!(log.mean_free_path(), f64::INFINITY);
+        assert_eq!(log.mean_free_time(), f64::INFINITY);
+
+        // Free flight with no partner to hit, and a resting ball
+        let mut world = World::new(
+            30.0,
+            10.0,
+            vec![Ball::new(Vec2::new(15.0, 5.0), Vec2::ZERO, 1.0, 1.0)],
+        );
+        world.record_collisions(CollisionLog::new());
+        world.step(0.5);
+        let log = world.collision_log().unwrap();
+        assert_eq!(log.total(), 0);
+        assert_eq!(log.collision_frequency(), 0.0);
+        assert_eq!(log.mean_free_path(), f64::INFINITY);
+        assert_eq!(log.mean_free_time(), f64::INFINITY);
+    }
+
+    #[test]
+    fn mean_free_path_matches_kinetic_theory() {
+        // kT / m = 100
+        let fraction = 0.05;
+        let mut world = hard_disk_gas(400, fraction, 10.0, 7);
+        world.record_collisions(CollisionLog::new());
+
+        for _ in 0..4000 {
+            world.step(0.005);
+        }
 
-        assert!((before_p.x - after_p.x).abs() < 1e-3);
-        assert!((before_p.y - after_p.y).abs() < 1e-3);
-        assert!((before_e - after_e).abs() < 1e-3);
+        let log = world.collision_log().unwrap();
+        assert_eq!(log.counts().iter().sum::<u64>(), 2 * log.total());
+        assert!(log.total() > 1000);
+        // Enskog: the dilute value shortened by the contact value
+        // g(σ) = (1 - 7φ/16) / (1 - φ)²
+        let contact = (1.0 - 7.0 * fraction / 16.0) / (1.0 - fraction).powi(2);
+        let expected = world.kinetic_mean_free_path() / contact;
+        let measured = log.mean_free_path() as f32;
+        assert!(
+            (measured / expected - 1.0).abs() < 0.1,
+            "λ = {measured}, kinetic theory {expected}"
+        );
     }
 }
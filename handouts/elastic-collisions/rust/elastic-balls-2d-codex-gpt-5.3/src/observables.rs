@@ -0,0 +1,105 @@
+//! Macroscopic observables of a `World`: energies, momenta, temperature and
+//! pressure (k_B = 1 throughout).
+
+use crate::{Vector, World};
+
+/// Sums accumulated by `World::step` since the last `World::reset_pressure`,
+/// from which `World::pressure` is computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PressureWindow {
+    /// Simulated time covered.
+    pub duration: f32,
+    /// Time integral of `2 E_trans / D`, the ideal-gas part of `P V`.
+    pub kinetic: f32,
+    /// Collisional virial `Σ r_ij · J_ij` over all ball-ball impulses, with
+    /// `r_ij` the centre separation and `J_ij` the impulse on `j`.
+    pub collisional: f32,
+}
+
+impl PressureWindow {
+    /// Time-averaged pressure in a box of the given volume (area in 2D):
+    /// `P V = <2 E_trans / D> + Σ r_ij · J_ij / (D τ)`.
+    pub fn pressure(&self, dim: usize, volume: f32) -> f32 {
+        if self.duration <= 0.0 {
+            return 0.0;
+        }
+        (self.kinetic + self.collisional / dim as f32) / (self.duration * volume)
+    }
+}
+
+impl<V: Vector> World<V> {
+    /// Area of the box in 2D, volume in 3D.
+    pub fn volume(&self) -> f32 {
+        (0..V::DIM).map(|k| self.size[k]).product()
+    }
+
+    /// Kinetic energy of the centre-of-mass motion of all balls.
+    pub fn translational_energy(&self) -> f32 {
+        self.balls
+            .iter()
+            .map(|b| 0.5 * b.mass * b.velocity.length_squared())
+            .sum()
+    }
+
+    pub fn rotational_energy(&self) -> f32 {
+        self.balls
+            .iter()
+            .map(|b| 0.5 * b.moment_of_inertia * V::spin_length_squared(b.angular_velocity))
+            .sum()
+    }
+
+    /// Total kinetic energy, translational plus rotational. Conserved by
+    /// smooth and perfectly rough contacts and by specular walls.
+    pub fn kinetic_energy(&self) -> f32 {
+        self.translational_energy() + self.rotational_energy()
+    }
+
+    /// Total kinetic energy per ball (zero for an empty world).
+    pub fn mean_kinetic_energy(&self) -> f32 {
+        if self.balls.is_empty() {
+            return 0.0;
+        }
+        self.kinetic_energy() / self.balls.len() as f32
+    }
+
+    pub fn momentum(&self) -> V {
+        self.balls
+            .iter()
+            .fold(V::ZERO, |p, b| p + b.velocity * b.mass)
+    }
+
+    /// Orbital plus spin angular momentum about the centre of the box.
+    pub fn angular_momentum(&self) -> V::Spin {
+        let center = self.size * 0.5;
+        self.balls.iter().fold(V::Spin::default(), |l, b| {
+            l + (b.position - center).cross(b.velocity * b.mass)
+                + b.angular_velocity * b.moment_of_inertia
+        })
+    }
+
+    /// Kinetic temperature from equipartition of the translational energy,
+    /// `kT = 2 E_trans / (D N)`: `E_trans / N` for disks.
+    pub fn temperature(&self) -> f32 {
+        if self.balls.is_empty() {
+            return 0.0;
+        }
+        2.0 * self.translational_energy() / (V::DIM * self.balls.len()) as f32
+    }
+
+    /// Pressure averaged over the steps since the last `reset_pressure`, from
+    /// the kinetic term plus the collisional virial of the ball-ball
+    /// impulses. Wall impulses are not counted, so in a box with walls this is
+    /// the bulk pressure of the gas. Zero before the first step.
+    pub fn pressure(&self) -> f32 {
+        self.pressure_window.pressure(V::DIM, self.volume())
+    }
+
+    pub fn pressure_window(&self) -> PressureWindow {
+        self.pressure_window
+    }
+
+    /// Start a new averaging window for `pressure`.
+    pub fn reset_pressure(&mut self) {
+        self.pressure_window = PressureWindow::default();
+    }
+}
@@ -0,0 +1,212 @@
+//! Radial distribution function `g(r)` of the ball centres, accumulated by
+//! `World::step` while a `RadialDistribution` is attached with
+//! `World::record_rdf`.
+
+use crate::{Vector, World};
+use std::f32::consts::{PI, TAU};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Histogram of centre-centre distances up to `range`, normalised by the
+/// number of pairs an ideal gas at the same density would put in each shell.
+///
+/// Shells reaching past a wall are edge corrected: the ideal count around
+/// each ball only includes the part of the shell inside the box, so `g(r)`
+/// of an ideal gas is 1 for every `r` rather than falling off once shells
+/// leave the box. The box has no periodic images, so distances are plain
+/// Euclidean ones.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RadialDistribution {
+    range: f32,
+    /// Sample every `interval`-th step.
+    interval: usize,
+    steps: usize,
+    frames: usize,
+    /// Pairs per bin, each unordered pair counted once.
+    pairs: Vec<u64>,
+    /// Expected pairs per bin for an ideal gas, summed over frames.
+    ideal: Vec<f64>,
+}
+
+impl RadialDistribution {
+    /// `bins` bins between 0 and `range`, sampling one step in every
+    /// `interval`. Sampled steps widen the cell-list cells to `range`, so a
+    /// large range or interval of 1 slows stepping down.
+    pub fn new(range: f32, bins: usize, interval: usize) -> Self {
+        let bins = bins.max(1);
+        Self {
+            range,
+            interval: interval.max(1),
+            steps: 0,
+            frames: 0,
+            pairs: vec![0; bins],
+            ideal: vec![0.0; bins],
+        }
+    }
+
+    pub fn range(&self) -> f32 {
+        self.range
+    }
+
+    pub fn bin_width(&self) -> f32 {
+        self.range / self.pairs.len() as f32
+    }
+
+    /// Number of frames sampled so far.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Count the step about to be taken; true if it should be sampled.
+    pub(crate) fn due(&mut self) -> bool {
+        self.steps += 1;
+        (self.steps - 1).is_multiple_of(self.interval)
+    }
+
+    pub(crate) fn add_pair(&mut self, distance: f32) {
+        if distance < self.range {
+            let last = self.pairs.len() - 1;
+            let bin = (distance / self.bin_width()) as usize;
+            self.pairs[bin.min(last)] += 1;
+        }
+    }
+
+    /// Close a sampled frame by adding the ideal-gas pair counts for balls at
+    /// `positions` in a box from the origin to `size`.
+    pub(crate) fn add_frame<V: Vector>(&mut self, positions: &[V], size: V) {
+        let count = positions.len();
+        if count < 2 {
+            return;
+        }
+        let volume: f32 = (0..V::DIM).map(|k| size[k]).product();
+        // Half the other balls' density, since each pair is counted once
+        let density = 0.5 * (count - 1) as f32 / volume;
+
+        let width = self.bin_width();
+        for (bin, ideal) in self.ideal.iter_mut().enumerate() {
+            let (inner, outer) = (bin as f32 * width, (bin + 1) as f32 * width);
+            let shell = match V::DIM {
+                2 => PI * (outer * outer - inner * inner),
+                _ => 4.0 / 3.0 * PI * (outer.powi(3) - inner.powi(3)),
+            };
+            let r = 0.5 * (inner + outer);
+            let inside: f32 = positions
+                .iter()
+                .map(|&p| shell_fraction_inside(p, size, r))
+                .sum();
+            *ideal += f64::from(density * shell * inside);
+        }
+        self.frames += 1;
+    }
+
+    /// `(r, g(r))` at the bin centres, averaged over all sampled frames.
+    pub fn values(&self) -> Vec<(f32, f32)> {
+        let width = self.bin_width();
+        self.pairs
+            .iter()
+            .zip(&self.ideal)
+            .enumerate()
+            .map(|(bin, (&pairs, &ideal))| {
+                let g = if ideal > 0.0 {
+                    (pairs as f64 / ideal) as f32
+                } else {
+                    0.0
+                };
+                ((bin as f32 + 0.5) * width, g)
+            })
+            .collect()
+    }
+
+    /// Two columns, `r` and `g(r)`, one bin per line after a `#` header.
+    pub fn write(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "# r g(r)  ({} frames)", self.frames)?;
+        for (r, g) in self.values() {
+            writeln!(out, "{r} {g}")?;
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        self.write(&mut out)?;
+        out.flush()
+    }
+}
+
+impl<V: Vector> World<V> {
+    /// Start accumulating `g(r)` into `rdf` on the steps it samples,
+    /// replacing any accumulator already attached.
+    pub fn record_rdf(&mut self, rdf: RadialDistribution) {
+        self.rdf = Some(rdf);
+    }
+
+    pub fn rdf(&self) -> Option<&RadialDistribution> {
+        self.rdf.as_ref()
+    }
+
+    /// Stop accumulating and hand back the accumulator.
+    pub fn take_rdf(&mut self) -> Option<RadialDistribution> {
+        self.rdf.take()
+    }
+}
+
+/// Fraction of the circle (2D) or sphere surface (3D) of radius `r` around
+/// `center` that lies inside the box from the origin to `size`.
+fn shell_fraction_inside<V: Vector>(center: V, size: V, r: f32) -> f32 {
+    let (x, y) = (center[0], center[1]);
+    if V::DIM == 2 {
+        return arc_fraction_inside(x, y, r, size[0], size[1]);
+    }
+
+    // Archimedes: the surface is uniform in height, so average the
+    // horizontal circles over evenly spaced slices
+    const SLICES: usize = 32;
+    let total: f32 = (0..SLICES)
+        .map(|k| {
+            let u = (2.0 * k as f32 + 1.0) / SLICES as f32 - 1.0;
+            let z = center[2] + u * r;
+            if z < 0.0 || z > size[2] {
+                return 0.0;
+            }
+            arc_fraction_inside(x, y, r * (1.0 - u * u).sqrt(), size[0], size[1])
+        })
+        .sum();
+    total / SLICES as f32
+}
+
+/// Fraction of the circle of radius `r` around `(x, y)` inside the rectangle
+/// `[0, width] × [0, height]`.
+fn arc_fraction_inside(x: f32, y: f32, r: f32, width: f32, height: f32) -> f32 {
+    if r <= 0.0 {
+        return 1.0;
+    }
+
+    // Angles where the circle crosses the lines bounding the rectangle
+    let mut angles = vec![0.0, TAU];
+    for edge in [-x, width - x] {
+        if edge.abs() < r {
+            let a = (edge / r).acos();
+            angles.extend([a, TAU - a]);
+        }
+    }
+    for edge in [-y, height - y] {
+        if edge.abs() < r {
+            let a = (edge / r).asin().rem_euclid(TAU);
+            angles.extend([a, (PI - a).rem_euclid(TAU)]);
+        }
+    }
+    angles.sort_by(f32::total_cmp);
+
+    // Each arc between crossings is wholly inside or outside; test its middle
+    let inside: f32 = angles
+        .windows(2)
+        .filter(|w| {
+            let mid = 0.5 * (w[0] + w[1]);
+            let (px, py) = (x + r * mid.cos(), y + r * mid.sin());
+            (0.0..=width).contains(&px) && (0.0..=height).contains(&py)
+        })
+        .map(|w| w[1] - w[0])
+        .sum();
+    inside / TAU
+}
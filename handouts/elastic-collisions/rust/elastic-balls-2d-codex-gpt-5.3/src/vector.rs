@@ -0,0 +1,124 @@
+use glam::{Quat, Vec2, Vec3};
+use std::f32::consts::TAU;
+use std::fmt::Debug;
+use std::ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign};
+
+/// Position/velocity type of a `World`: `Vec2` for disks in a rectangle,
+/// `Vec3` for spheres in a box. At most three dimensions are supported.
+pub trait Vector:
+    Copy
+    + Debug
+    + PartialEq
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<f32, Output = Self>
+    + Div<f32, Output = Self>
+    + Neg<Output = Self>
+    + AddAssign
+    + SubAssign
+    + Index<usize, Output = f32>
+    + IndexMut<usize>
+{
+    const DIM: usize;
+    const ZERO: Self;
+    const X: Self;
+    /// Moment of inertia of a uniform ball about its centre, in units of m r².
+    const INERTIA_FACTOR: f32;
+
+    /// Angular velocity: a scalar in 2D, an axial vector in 3D.
+    type Spin: Copy
+        + Debug
+        + PartialEq
+        + Default
+        + Add<Output = Self::Spin>
+        + Mul<f32, Output = Self::Spin>
+        + AddAssign
+        + SubAssign;
+    /// Rotation angle in 2D, unit quaternion in 3D.
+    type Orientation: Copy + Debug + PartialEq;
+
+    const IDENTITY: Self::Orientation;
+
+    fn dot(self, other: Self) -> f32;
+
+    fn length_squared(self) -> f32 {
+        self.dot(self)
+    }
+
+    /// Velocity `ω × r` of a point at `r` on a body spinning with `ω`.
+    fn spin_cross(spin: Self::Spin, r: Self) -> Self;
+
+    /// `|ω|²`, e.g. for rotational kinetic energy.
+    fn spin_length_squared(spin: Self::Spin) -> f32;
+
+    /// Cross product `self × other` (a scalar in 2D), e.g. a torque.
+    fn cross(self, other: Self) -> Self::Spin;
+
+    /// Orientation after spinning at `spin` for `dt`.
+    fn rotate(orientation: Self::Orientation, spin: Self::Spin, dt: f32) -> Self::Orientation;
+}
+
+impl Vector for Vec2 {
+    const DIM: usize = 2;
+    const ZERO: Self = Vec2::ZERO;
+    const X: Self = Vec2::X;
+    const INERTIA_FACTOR: f32 = 0.5;
+
+    type Spin = f32;
+    /// Angle in radians, in `[0, 2π)`, positive from +x towards +y.
+    type Orientation = f32;
+
+    const IDENTITY: f32 = 0.0;
+
+    fn dot(self, other: Self) -> f32 {
+        Vec2::dot(self, other)
+    }
+
+    fn spin_cross(spin: f32, r: Self) -> Self {
+        r.perp() * spin
+    }
+
+    fn spin_length_squared(spin: f32) -> f32 {
+        spin * spin
+    }
+
+    fn cross(self, other: Self) -> f32 {
+        self.perp_dot(other)
+    }
+
+    fn rotate(orientation: f32, spin: f32, dt: f32) -> f32 {
+        (orientation + spin * dt).rem_euclid(TAU)
+    }
+}
+
+impl Vector for Vec3 {
+    const DIM: usize = 3;
+    const ZERO: Self = Vec3::ZERO;
+    const X: Self = Vec3::X;
+    const INERTIA_FACTOR: f32 = 0.4;
+
+    type Spin = Vec3;
+    type Orientation = Quat;
+
+    const IDENTITY: Quat = Quat::IDENTITY;
+
+    fn dot(self, other: Self) -> f32 {
+        Vec3::dot(self, other)
+    }
+
+    fn spin_cross(spin: Vec3, r: Self) -> Self {
+        spin.cross(r)
+    }
+
+    fn spin_length_squared(spin: Vec3) -> f32 {
+        spin.length_squared()
+    }
+
+    fn cross(self, other: Self) -> Vec3 {
+        Vec3::cross(self, other)
+    }
+
+    fn rotate(orientation: Quat, spin: Vec3, dt: f32) -> Quat {
+        (Quat::from_scaled_axis(spin * dt) * orientation).normalize()
+    }
+}
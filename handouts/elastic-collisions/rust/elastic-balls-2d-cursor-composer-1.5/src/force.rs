@@ -0,0 +1,60 @@
+//! External forces acting on individual balls.
+
+use crate::Ball;
+use macroquad::math::Vec2;
+use std::fmt;
+
+/// An external force applied to every ball.
+pub enum ExternalForce {
+    /// Constant acceleration `g` in pixels/s², the same for every ball; the
+    /// force on a ball is `g` times its mass.
+    Gravity(Vec2),
+    /// Spring pulling each ball back to `center`, with force
+    /// `-stiffness * (pos - center)` whatever the ball's mass.
+    Harmonic { center: Vec2, stiffness: f32 },
+    /// Any other force, as a closure of the ball (drag, a wind field, ...).
+    /// Counts as zero in [`ExternalForce::potential_energy`].
+    Custom(Box<dyn Fn(&Ball) -> Vec2>),
+}
+
+impl ExternalForce {
+    /// Wrap a closure as a custom force.
+    pub fn custom(force: impl Fn(&Ball) -> Vec2 + 'static) -> Self {
+        ExternalForce::Custom(Box::new(force))
+    }
+
+    /// Force exerted on `ball`.
+    pub fn force(&self, ball: &Ball) -> Vec2 {
+        match self {
+            ExternalForce::Gravity(g) => *g * ball.mass,
+            ExternalForce::Harmonic { center, stiffness } => -(ball.pos - *center) * *stiffness,
+            ExternalForce::Custom(force) => force(ball),
+        }
+    }
+
+    /// Potential energy of `ball`; custom forces are not assumed conservative
+    /// and contribute zero.
+    pub fn potential_energy(&self, ball: &Ball) -> f32 {
+        match self {
+            ExternalForce::Gravity(g) => -ball.mass * g.dot(ball.pos),
+            ExternalForce::Harmonic { center, stiffness } => {
+                0.5 * stiffness * (ball.pos - *center).length_squared()
+            }
+            ExternalForce::Custom(_) => 0.0,
+        }
+    }
+}
+
+impl fmt::Debug for ExternalForce {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExternalForce::Gravity(g) => f.debug_tuple("Gravity").field(g).finish(),
+            ExternalForce::Harmonic { center, stiffness } => f
+                .debug_struct("Harmonic")
+                .field("center", center)
+                .field("stiffness", stiffness)
+                .finish(),
+            ExternalForce::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Time integrators for the free-flight part of a step.
+//!
+//! Collisions are resolved separately after the integrator has moved the
+//! balls, so an integrator only has to handle smooth forces.
+
+use crate::Ball;
+use macroquad::math::Vec2;
+use std::fmt::Debug;
+
+/// Fills `acc` with the acceleration of each ball at its current position.
+pub type Accelerations<'a> = dyn FnMut(&[Ball], &mut [Vec2]) + 'a;
+
+/// Advances ball positions and velocities over one time step.
+pub trait Integrator: Debug {
+    fn integrate(&self, balls: &mut [Ball], dt: f32, accel: &mut Accelerations);
+}
+
+/// Forward (explicit) Euler: first order, energy grows without bound.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExplicitEuler;
+
+/// Symplectic (semi-implicit) Euler: first order, bounded energy error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SymplecticEuler;
+
+/// Velocity Verlet: second order, symplectic.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VelocityVerlet;
+
+/// Forest–Ruth: fourth-order symplectic composition of Verlet steps
+/// (equivalent to Yoshida's fourth-order scheme).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ForestRuth;
+
+impl Integrator for ExplicitEuler {
+    fn integrate(&self, balls: &mut [Ball], dt: f32, accel: &mut Accelerations) {
+        let mut acc = vec![Vec2::ZERO; balls.len()];
+        accel(balls, &mut acc);
+        for (ball, a) in balls.iter_mut().zip(&acc) {
+            ball.pos += ball.vel * dt;
+            ball.vel += *a * dt;
+        }
+    }
+}
+
+impl Integrator for SymplecticEuler {
+    fn integrate(&self, balls: &mut [Ball], dt: f32, accel: &mut Accelerations) {
+        let mut acc = vec![Vec2::ZERO; balls.len()];
+        accel(balls, &mut acc);
+        kick(balls, &acc, dt);
+        drift(balls, dt);
+    }
+}
+
+impl Integrator for VelocityVerlet {
+    fn integrate(&self, balls: &mut [Ball], dt: f32, accel: &mut Accelerations) {
+        let mut acc = vec![Vec2::ZERO; balls.len()];
+        accel(balls, &mut acc);
+        kick(balls, &acc, 0.5 * dt);
+        drift(balls, dt);
+        accel(balls, &mut acc);
+        kick(balls, &acc, 0.5 * dt);
+    }
+}
+
+impl Integrator for ForestRuth {
+    fn integrate(&self, balls: &mut [Ball], dt: f32, accel: &mut Accelerations) {
+        // θ = 1 / (2 - 2^(1/3))
+        let theta = 1.0 / (2.0 - 2.0_f32.cbrt());
+        let mut acc = vec![Vec2::ZERO; balls.len()];
+
+        drift(balls, 0.5 * theta * dt);
+        accel(balls, &mut acc);
+        kick(balls, &acc, theta * dt);
+        drift(balls, 0.5 * (1.0 - theta) * dt);
+        accel(balls, &mut acc);
+        kick(balls, &acc, (1.0 - 2.0 * theta) * dt);
+        drift(balls, 0.5 * (1.0 - theta) * dt);
+        accel(balls, &mut acc);
+        kick(balls, &acc, theta * dt);
+        drift(balls, 0.5 * theta * dt);
+    }
+}
+
+fn drift(balls: &mut [Ball], dt: f32) {
+    for ball in balls.iter_mut() {
+        ball.pos += ball.vel * dt;
+    }
+}
+
+fn kick(balls: &mut [Ball], acc: &[Vec2], dt: f32) {
+    for (ball, a) in balls.iter_mut().zip(acc) {
+        ball.vel += *a * dt;
+    }
+}
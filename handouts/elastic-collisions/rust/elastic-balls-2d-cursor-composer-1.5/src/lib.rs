@@ -1,10 +1,36 @@
 //! Elastic collision simulation for multiple balls in 2D space.
 //!
-//! This crate provides physics simulation for balls that collide elastically
-//! with each other and with the boundaries of a rectangular world.
+//! This crate provides physics simulation for balls that collide with each
+//! other, with the boundaries of a rectangular world and with static
+//! [`Obstacle`]s inside it. Collisions are perfectly elastic by default; lower
+//! restitution coefficients model granular (dissipative) gases. Between
+//! collisions, balls move under optional external forces advanced by a
+//! pluggable [`Integrator`]. Alternatively, a smooth [`PairPotential`]
+//! (Lennard-Jones, WCA, Yukawa, ...) replaces hard ball-ball collisions for
+//! molecular-dynamics demos. [`World::thermalize`] draws Maxwell–Boltzmann
+//! initial velocities at a given temperature, and a
+//! [`VelocityAutocorrelation`] attached to the world measures the
+//! self-diffusion coefficient by Green–Kubo integration.
 
 use macroquad::math::Vec2;
-use std::f32::consts::PI;
+use rand::Rng;
+use std::f32::consts::{PI, TAU};
+
+mod force;
+mod integrator;
+mod obstacle;
+mod potential;
+mod vacf;
+
+pub use force::ExternalForce;
+pub use integrator::{
+    Accelerations, ExplicitEuler, ForestRuth, Integrator, SymplecticEuler, VelocityVerlet,
+};
+pub use obstacle::Obstacle;
+pub use potential::PairPotential;
+pub use vacf::VelocityAutocorrelation;
+
+use potential::NeighbourList;
 
 /// A ball with position, velocity, radius, and mass.
 /// Mass is proportional to area (πr²) for uniform density.
@@ -15,10 +41,13 @@ pub struct Ball {
     pub radius: f32,
     pub mass: f32,
     pub color: [f32; 4],
+    /// Normal coefficient of restitution of the ball's material (1 = elastic).
+    pub restitution: f32,
 }
 
 impl Ball {
     /// Create a new ball. Mass is computed from radius assuming unit density.
+    /// The ball is perfectly elastic; see [`Ball::with_restitution`].
     pub fn new(pos: Vec2, vel: Vec2, radius: f32, color: [f32; 4]) -> Self {
         Self {
             pos,
@@ -26,8 +55,42 @@ impl Ball {
             radius,
             mass: PI * radius * radius,
             color,
+            restitution: 1.0,
         }
     }
+
+    /// Set the normal coefficient of restitution (0 = perfectly plastic, 1 = elastic).
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = restitution;
+        self
+    }
+
+    /// Kinetic energy ½mv².
+    pub fn kinetic_energy(&self) -> f32 {
+        0.5 * self.mass * self.vel.length_squared()
+    }
+}
+
+/// Restitution used for a collision between two materials: the geometric
+/// mean, so two balls of the same material collide with that material's value.
+pub fn pair_restitution(a: &Ball, b: &Ball) -> f32 {
+    (a.restitution * b.restitution).sqrt()
+}
+
+/// Kinetic energy removed by inelastic collisions since the last reset.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct EnergyLoss {
+    /// Dissipated in ball-ball collisions.
+    pub collisions: f32,
+    /// Dissipated in ball-wall collisions.
+    pub walls: f32,
+}
+
+impl EnergyLoss {
+    /// Total dissipated energy.
+    pub fn total(&self) -> f32 {
+        self.collisions + self.walls
+    }
 }
 
 /// The simulation world containing balls and boundaries.
@@ -38,6 +101,26 @@ pub struct World {
     pub height: f32,
     pub paused: bool,
     pub speed_multiplier: f32,
+    /// Normal coefficient of restitution of the four walls (1 = elastic).
+    pub wall_restitution: f32,
+    /// Energy dissipated so far; reset with [`World::reset_energy_loss`].
+    pub energy_loss: EnergyLoss,
+    /// External forces acting on every ball.
+    pub forces: Vec<ExternalForce>,
+    /// Scheme used to advance positions and velocities between collisions.
+    pub integrator: Box<dyn Integrator>,
+    /// Static obstacles inside the box. They share the wall restitution.
+    pub obstacles: Vec<Obstacle>,
+    /// Smooth ball-ball interaction replacing hard collisions, if any; set
+    /// with [`World::set_pair_potential`].
+    pair_potential: Option<PairPotential>,
+    /// Verlet-list skin: extra range beyond the cutoff, so the neighbour
+    /// list only has to be rebuilt after a ball moved half this far.
+    neighbour_skin: f32,
+    neighbours: NeighbourList,
+    /// Velocity autocorrelation sampled during [`World::update`]; attach
+    /// with [`World::record_vacf`].
+    vacf: Option<VelocityAutocorrelation>,
 }
 
 impl World {
@@ -49,17 +132,131 @@ impl World {
             height,
             paused: false,
             speed_multiplier: 1.0,
+            wall_restitution: 1.0,
+            energy_loss: EnergyLoss::default(),
+            forces: Vec::new(),
+            integrator: Box::new(VelocityVerlet),
+            obstacles: Vec::new(),
+            pair_potential: None,
+            neighbour_skin: 0.0,
+            neighbours: NeighbourList::default(),
+            vacf: None,
         }
     }
 
+    /// Choose the time integrator.
+    pub fn set_integrator(&mut self, integrator: impl Integrator + 'static) {
+        self.integrator = Box::new(integrator);
+    }
+
+    /// Add a static obstacle.
+    pub fn add_obstacle(&mut self, obstacle: Obstacle) {
+        self.obstacles.push(obstacle);
+    }
+
+    /// Register an external force.
+    pub fn add_force(&mut self, force: ExternalForce) {
+        self.forces.push(force);
+    }
+
+    /// Switch ball-ball interactions to a smooth pair potential (integrated
+    /// by the world's integrator, velocity Verlet by default), or back to
+    /// hard collisions with `None`. `skin` is the Verlet neighbour-list skin
+    /// in world units.
+    pub fn set_pair_potential(&mut self, potential: Option<PairPotential>, skin: f32) {
+        self.pair_potential = potential;
+        self.neighbour_skin = skin;
+        self.neighbours.invalidate();
+    }
+
+    /// The active pair potential, if any.
+    pub fn pair_potential(&self) -> Option<&PairPotential> {
+        self.pair_potential.as_ref()
+    }
+
     /// Add a ball to the world.
     pub fn add_ball(&mut self, ball: Ball) {
         self.balls.push(ball);
+        self.neighbours.invalidate();
     }
 
     /// Remove all balls.
     pub fn clear(&mut self) {
         self.balls.clear();
+        self.neighbours.invalidate();
+        self.reset_energy_loss();
+    }
+
+    /// Total kinetic energy of all balls.
+    pub fn kinetic_energy(&self) -> f32 {
+        self.balls.iter().map(Ball::kinetic_energy).sum()
+    }
+
+    /// Total potential energy in the external forces and, if set, the pair
+    /// potential.
+    pub fn potential_energy(&self) -> f32 {
+        let external: f32 = self
+            .balls
+            .iter()
+            .flat_map(|ball| self.forces.iter().map(|f| f.potential_energy(ball)))
+            .sum();
+
+        let Some(potential) = &self.pair_potential else {
+            return external;
+        };
+        let mut pair = 0.0;
+        for (i, a) in self.balls.iter().enumerate() {
+            for b in &self.balls[i + 1..] {
+                pair += potential.energy(a.pos.distance(b.pos), a.radius + b.radius);
+            }
+        }
+        external + pair
+    }
+
+    /// Granular temperature: mean kinetic energy per degree of freedom (k_B = 1).
+    pub fn temperature(&self) -> f32 {
+        if self.balls.is_empty() {
+            return 0.0;
+        }
+        self.kinetic_energy() / (2.0 * self.balls.len() as f32)
+    }
+
+    /// Draw fresh velocities from the Maxwell–Boltzmann distribution at
+    /// temperature `kt` (k_B = 1): each component Gaussian with variance
+    /// kT/m, so heavy balls move slower. The centre-of-mass velocity is then
+    /// removed and the velocities rescaled so that the kinetic energy is
+    /// exactly (N − 1) kT, equipartition over the 2(N − 1) degrees of freedom
+    /// left once the total momentum is zero. No thermostat acts afterwards.
+    pub fn thermalize(&mut self, kt: f32, rng: &mut impl Rng) {
+        for ball in &mut self.balls {
+            let sigma = (kt / ball.mass).sqrt();
+            ball.vel = Vec2::new(gaussian(rng), gaussian(rng)) * sigma;
+        }
+
+        let total_mass: f32 = self.balls.iter().map(|b| b.mass).sum();
+        if total_mass <= 0.0 {
+            return;
+        }
+        let drift = self.balls.iter().map(|b| b.vel * b.mass).sum::<Vec2>() / total_mass;
+        for ball in &mut self.balls {
+            ball.vel -= drift;
+        }
+
+        let target = (self.balls.len() - 1) as f32 * kt;
+        let energy = self.kinetic_energy();
+        let scale = if energy > 0.0 {
+            (target / energy).sqrt()
+        } else {
+            0.0
+        };
+        for ball in &mut self.balls {
+            ball.vel *= scale;
+        }
+    }
+
+    /// Zero the dissipated-energy counters.
+    pub fn reset_energy_loss(&mut self) {
+        self.energy_loss = EnergyLoss::default();
     }
 
     /// Number of balls in the world.
@@ -85,40 +282,97 @@ impl World {
         let mut remaining = total_dt;
 
         while remaining > 0.0 {
-            let sub_dt = remaining.min(max_sub_dt);
+            let mut sub_dt = remaining.min(max_sub_dt);
+            // End a sub-step on every VACF sample, so that its lags are exact
+            // multiples of the sampling interval
+            if let Some(vacf) = &self.vacf {
+                sub_dt = sub_dt.min(vacf.until_sample());
+            }
             remaining -= sub_dt;
             self.step(sub_dt);
+            if let Some(vacf) = &mut self.vacf {
+                vacf.advance(sub_dt, &self.balls);
+            }
         }
     }
 
     fn step(&mut self, dt: f32) {
-        // Integrate position
-        for ball in self.balls.iter_mut() {
-            ball.pos += ball.vel * dt;
-        }
+        let potential = self.pair_potential.as_ref();
+        let pairs = match potential {
+            Some(potential) => self
+                .neighbours
+                .update(&self.balls, potential, self.neighbour_skin),
+            None => &[],
+        };
 
-        // Wall collisions (elastic bounce)
+        // Integrate equations of motion
+        let forces = &self.forces;
+        self.integrator.integrate(
+            &mut self.balls,
+            dt,
+            &mut |balls: &[Ball], acc: &mut [Vec2]| {
+                for (a, ball) in acc.iter_mut().zip(balls) {
+                    *a = forces.iter().map(|f| f.force(ball)).sum::<Vec2>();
+                }
+                if let Some(potential) = potential {
+                    for &(i, j) in pairs {
+                        let f = potential.force(&balls[i], &balls[j]);
+                        acc[i] -= f;
+                        acc[j] += f;
+                    }
+                }
+                for (a, ball) in acc.iter_mut().zip(balls) {
+                    *a /= ball.mass;
+                }
+            },
+        );
+
+        // Wall collisions
+        let e_wall = self.wall_restitution;
         for ball in self.balls.iter_mut() {
             let r = ball.radius;
+            let m = ball.mass;
+            let mut lost = 0.0;
 
             if ball.pos.x - r < 0.0 {
-                ball.vel.x = ball.vel.x.abs();
+                lost += bounce(&mut ball.vel.x, 1.0, e_wall, m);
                 ball.pos.x = r;
             } else if ball.pos.x + r > self.width {
-                ball.vel.x = -ball.vel.x.abs();
+                lost += bounce(&mut ball.vel.x, -1.0, e_wall, m);
                 ball.pos.x = self.width - r;
             }
 
             if ball.pos.y - r < 0.0 {
-                ball.vel.y = ball.vel.y.abs();
+                lost += bounce(&mut ball.vel.y, 1.0, e_wall, m);
                 ball.pos.y = r;
             } else if ball.pos.y + r > self.height {
-                ball.vel.y = -ball.vel.y.abs();
+                lost += bounce(&mut ball.vel.y, -1.0, e_wall, m);
                 ball.pos.y = self.height - r;
             }
+
+            self.energy_loss.walls += lost;
+        }
+
+        // Obstacle collisions
+        for ball in self.balls.iter_mut() {
+            for obstacle in &self.obstacles {
+                // A few passes so a ball wedged into a concave corner is
+                // pushed out of every edge it touches
+                for _ in 0..4 {
+                    let Some(contact) = obstacle.contact(ball.pos, ball.vel, ball.radius) else {
+                        break;
+                    };
+                    ball.pos += contact.normal * contact.depth;
+                    self.energy_loss.walls += reflect(ball, contact.normal, e_wall);
+                }
+            }
         }
 
-        // Ball-ball elastic collisions
+        // Ball-ball collisions, unless the pair potential already keeps the
+        // balls apart
+        if self.pair_potential.is_some() {
+            return;
+        }
         let len = self.balls.len();
         for i in 0..len {
             for j in (i + 1)..len {
@@ -146,14 +400,469 @@ impl World {
                     self.balls[i].pos -= normal * (overlap * m2 / total_mass);
                     self.balls[j].pos += normal * (overlap * m1 / total_mass);
 
-                    // Elastic collision: exchange momentum along normal
-                    // For equal-mass 1D collision: v1' = v2, v2' = v1
-                    // General formula: impulse = 2 * (v2 - v1) · n / (1/m1 + 1/m2)
-                    let impulse = 2.0 * vel_along_normal / total_mass;
+                    // Exchange momentum along normal
+                    // For equal-mass elastic 1D collision: v1' = v2, v2' = v1
+                    // General formula: impulse = (1 + e) * (v2 - v1) · n / (1/m1 + 1/m2)
+                    let e = pair_restitution(&self.balls[i], &self.balls[j]);
+                    let impulse = (1.0 + e) * vel_along_normal / total_mass;
                     self.balls[i].vel += normal * (impulse * m2);
                     self.balls[j].vel -= normal * (impulse * m1);
+
+                    // ΔE = ½ μ (1 - e²) v_n² with reduced mass μ
+                    let reduced_mass = m1 * m2 / total_mass;
+                    self.energy_loss.collisions +=
+                        0.5 * reduced_mass * (1.0 - e * e) * vel_along_normal * vel_along_normal;
+                }
+            }
+        }
+    }
+}
+
+/// Zero-mean, unit-variance Gaussian sample from two uniform draws, using
+/// the cosine branch of the Box–Muller transform.
+fn gaussian(rng: &mut impl Rng) -> f32 {
+    let u: f32 = rng.gen();
+    let v: f32 = rng.gen();
+    (-2.0 * (1.0 - u).ln()).sqrt() * (TAU * v).cos()
+}
+
+/// Reflect the ball's velocity off a surface with unit `normal` (pointing
+/// towards the ball), scaling the normal component by `restitution`. Returns
+/// the energy lost. A ball already moving away is left untouched.
+fn reflect(ball: &mut Ball, normal: Vec2, restitution: f32) -> f32 {
+    let v_n = ball.vel.dot(normal);
+    if v_n >= 0.0 {
+        return 0.0;
+    }
+    ball.vel -= normal * ((1.0 + restitution) * v_n);
+    0.5 * ball.mass * (1.0 - restitution * restitution) * v_n * v_n
+}
+
+/// Reflect velocity component `v` off a wall whose inward normal points
+/// along `inward` (±1), scaling it by `restitution`. Returns the energy lost.
+/// A ball already moving away from the wall is left untouched.
+fn bounce(v: &mut f32, inward: f32, restitution: f32, mass: f32) -> f32 {
+    let v_n = *v * inward;
+    if v_n >= 0.0 {
+        return 0.0;
+    }
+    *v = -v_n * restitution * inward;
+    0.5 * mass * (1.0 - restitution * restitution) * v_n * v_n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    /// Non-overlapping equal-radius balls with uniform random velocities.
+    fn granular_gas(count: usize, size: f32, radius: f32, restitution: f32) -> World {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut world = World::new(size, size);
+        while world.ball_count() < count {
+            let pos = Vec2::new(
+                rng.gen_range(radius..size - radius),
+                rng.gen_range(radius..size - radius),
+            );
+            if world
+                .balls
+                .iter()
+                .any(|b| (b.pos - pos).length() < b.radius + radius)
+            {
+                continue;
+            }
+            let vel = Vec2::new(rng.gen_range(-100.0..100.0), rng.gen_range(-100.0..100.0));
+            world.add_ball(Ball::new(pos, vel, radius, [1.0; 4]).with_restitution(restitution));
+        }
+        world
+    }
+
+    #[test]
+    fn wall_restitution_scales_normal_velocity() {
+        let mut world = World::new(100.0, 100.0);
+        world.wall_restitution = 0.5;
+        world.add_ball(Ball::new(
+            Vec2::new(95.0, 50.0),
+            Vec2::new(60.0, 30.0),
+            5.0,
+            [1.0; 4],
+        ));
+        let before = world.kinetic_energy();
+
+        world.update(0.05);
+
+        let ball = &world.balls[0];
+        assert!((ball.vel.x + 30.0).abs() < 1e-4);
+        assert!((ball.vel.y - 30.0).abs() < 1e-4);
+        let lost = before - world.kinetic_energy();
+        assert!((lost - world.energy_loss.walls).abs() < 1e-3 * before);
+        assert_eq!(world.energy_loss.collisions, 0.0);
+    }
+
+    #[test]
+    fn dissipated_energy_accounts_for_kinetic_energy_drop() {
+        let mut world = granular_gas(100, 500.0, 8.0, 0.7);
+        world.wall_restitution = 0.9;
+        let before = world.kinetic_energy();
+
+        for _ in 0..300 {
+            world.update(1.0 / 60.0);
+        }
+
+        let after = world.kinetic_energy();
+        assert!(after < 0.5 * before);
+        assert!(world.energy_loss.collisions > 0.0 && world.energy_loss.walls > 0.0);
+        assert!((before - after - world.energy_loss.total()).abs() < 1e-4 * before);
+    }
+
+    /// Haff's law for a freely cooling granular gas: T(t) = T₀ / (1 + t/τ)²,
+    /// so √(T₀/T) grows linearly in time.
+    #[test]
+    fn homogeneous_cooling_follows_haffs_law() {
+        let mut world = granular_gas(200, 800.0, 10.0, 0.8);
+        let t0 = world.temperature();
+
+        let mut samples = Vec::new();
+        for frame in 1..=1200 {
+            world.update(1.0 / 60.0);
+            if frame % 60 == 0 {
+                samples.push((frame as f32 / 60.0, (t0 / world.temperature()).sqrt()));
+            }
+        }
+
+        let n = samples.len() as f32;
+        let mean_t = samples.iter().map(|s| s.0).sum::<f32>() / n;
+        let mean_y = samples.iter().map(|s| s.1).sum::<f32>() / n;
+        let s_ty: f32 = samples
+            .iter()
+            .map(|s| (s.0 - mean_t) * (s.1 - mean_y))
+            .sum();
+        let s_tt: f32 = samples.iter().map(|s| (s.0 - mean_t).powi(2)).sum();
+        let s_yy: f32 = samples.iter().map(|s| (s.1 - mean_y).powi(2)).sum();
+        let slope = s_ty / s_tt;
+        let intercept = mean_y - slope * mean_t;
+        let r_squared = s_ty * s_ty / (s_tt * s_yy);
+
+        assert!(t0 / world.temperature() > 5.0, "gas did not cool");
+        assert!(r_squared > 0.99, "√(T₀/T) not linear: R² = {r_squared}");
+        assert!((intercept - 1.0).abs() < 0.1, "intercept {intercept}");
+    }
+
+    /// Largest relative deviation of the total energy of a ball orbiting in a
+    /// harmonic trap (ω = 12 rad/s, ω·dt = 0.1) over one simulated minute.
+    fn harmonic_energy_drift(integrator: impl Integrator + 'static) -> f32 {
+        let omega = 12.0;
+        let center = Vec2::new(200.0, 200.0);
+        let ball = Ball::new(
+            center + Vec2::new(100.0, 0.0),
+            Vec2::new(0.0, 50.0),
+            5.0,
+            [1.0; 4],
+        );
+
+        let mut world = World::new(400.0, 400.0);
+        world.set_integrator(integrator);
+        world.add_force(ExternalForce::Harmonic {
+            center,
+            stiffness: ball.mass * omega * omega,
+        });
+        world.add_ball(ball);
+
+        let e0 = world.kinetic_energy() + world.potential_energy();
+        let mut worst = 0.0_f32;
+        for _ in 0..3600 {
+            world.update(1.0 / 60.0);
+            let e = world.kinetic_energy() + world.potential_energy();
+            worst = worst.max(((e - e0) / e0).abs());
+        }
+        worst
+    }
+
+    #[test]
+    fn integrator_energy_drift_in_harmonic_trap() {
+        let euler = harmonic_energy_drift(ExplicitEuler);
+        let symplectic = harmonic_energy_drift(SymplecticEuler);
+        let verlet = harmonic_energy_drift(VelocityVerlet);
+        let forest_ruth = harmonic_energy_drift(ForestRuth);
+
+        assert!(euler > 1.0, "explicit Euler should blow up: {euler}");
+        assert!(symplectic < 0.1, "symplectic Euler: {symplectic}");
+        assert!(verlet < symplectic / 10.0, "velocity Verlet: {verlet}");
+        assert!(forest_ruth < verlet / 10.0, "Forest-Ruth: {forest_ruth}");
+    }
+
+    #[test]
+    fn segment_end_point_reflects_along_vertex_normal() {
+        let end = Vec2::new(100.0, 100.0);
+        let normal = Vec2::new(-1.0, -1.0).normalize();
+        let mut world = World::new(400.0, 400.0);
+        world.add_obstacle(Obstacle::Segment {
+            a: end,
+            b: Vec2::new(200.0, 100.0),
+        });
+        world.add_ball(Ball::new(end + normal * 4.9, -normal * 10.0, 5.0, [1.0; 4]));
+
+        world.update(1.0 / 120.0);
+
+        // Straight back out along the diagonal, not off the segment's face
+        let vel = world.balls[0].vel;
+        assert!((vel - normal * 10.0).length() < 1e-3, "{vel:?}");
+    }
+
+    #[test]
+    fn concave_polygon_corner_and_interior() {
+        // A "U" opening upwards; the notch spans x in (110, 140), y in (100, 150)
+        let u_shape = Obstacle::Polygon {
+            vertices: vec![
+                Vec2::new(100.0, 100.0),
+                Vec2::new(110.0, 100.0),
+                Vec2::new(110.0, 150.0),
+                Vec2::new(140.0, 150.0),
+                Vec2::new(140.0, 100.0),
+                Vec2::new(150.0, 100.0),
+                Vec2::new(150.0, 160.0),
+                Vec2::new(100.0, 160.0),
+            ],
+        };
+        let mut world = World::new(400.0, 400.0);
+        world.add_obstacle(u_shape);
+        // Wedged into the inner corner, touching both notch walls
+        world.add_ball(Ball::new(
+            Vec2::new(114.8, 145.2),
+            Vec2::new(-30.0, 30.0),
+            5.0,
+            [1.0; 4],
+        ));
+        // Centre inside the solid bottom bar, nearest to the notch floor
+        world.add_ball(Ball::new(
+            Vec2::new(125.0, 152.0),
+            Vec2::ZERO,
+            5.0,
+            [1.0; 4],
+        ));
+
+        world.update(1.0 / 120.0);
+
+        let corner = &world.balls[0];
+        assert!((corner.vel - Vec2::new(30.0, -30.0)).length() < 1e-3);
+        assert!(corner.pos.x >= 115.0 - 1e-3 && corner.pos.y <= 145.0 + 1e-3);
+
+        // Pushed out through the nearest edge, the notch floor
+        let buried = &world.balls[1];
+        assert!((buried.pos - Vec2::new(125.0, 145.0)).length() < 1e-3);
+    }
+
+    #[test]
+    fn pair_forces_are_minus_energy_gradient() {
+        let potentials = [
+            PairPotential::LennardJones {
+                epsilon: 2.0,
+                cutoff: 2.5,
+            },
+            PairPotential::Wca { epsilon: 2.0 },
+            PairPotential::Yukawa {
+                epsilon: 2.0,
+                screening: 1.5,
+                cutoff: 4.0,
+            },
+            PairPotential::custom(3.0, |r, sigma| {
+                let x = r / sigma - 3.0;
+                (x * x, 2.0 * x / sigma)
+            }),
+        ];
+        let a = Ball::new(Vec2::ZERO, Vec2::ZERO, 0.5, [1.0; 4]);
+
+        for potential in &potentials {
+            for k in 0..40 {
+                let r = 0.9 + 0.05 * k as f32;
+                let b = Ball::new(Vec2::new(r, 0.0), Vec2::ZERO, 0.5, [1.0; 4]);
+                let h = 1e-3;
+                let gradient =
+                    (potential.energy(r + h, 1.0) - potential.energy(r - h, 1.0)) / (2.0 * h);
+                let force = potential.force(&a, &b);
+                if (r - potential.cutoff(1.0)).abs() > h {
+                    assert!(
+                        (force.x + gradient).abs() < 2e-2 * (1.0 + gradient.abs()),
+                        "{potential:?} at r = {r}: {} vs {}",
+                        force.x,
+                        -gradient
+                    );
                 }
+                assert_eq!(force.y, 0.0);
             }
+            // Shifted to zero at the cutoff
+            assert!(potential.energy(potential.cutoff(1.0) - 1e-4, 1.0).abs() < 1e-2);
+        }
+    }
+
+    /// A 7 × 7 Lennard-Jones crystal with random thermal velocities.
+    fn lennard_jones_crystal(skin: f32) -> World {
+        let mut rng = StdRng::seed_from_u64(11);
+        let radius = 5.0;
+        let spacing = 2.0_f32.powf(1.0 / 6.0) * 2.0 * radius;
+        let mut world = World::new(300.0, 300.0);
+        for k in 0..49 {
+            let pos = Vec2::new(
+                110.0
+                    + spacing * (k % 7) as f32
+                    + if (k / 7) % 2 == 1 { 0.5 * spacing } else { 0.0 },
+                110.0 + spacing * 0.866 * (k / 7) as f32,
+            );
+            let vel = Vec2::new(rng.gen_range(-4.0..4.0), rng.gen_range(-4.0..4.0));
+            world.add_ball(Ball::new(pos, vel, radius, [1.0; 4]));
+        }
+        world.set_pair_potential(
+            Some(PairPotential::LennardJones {
+                epsilon: 1000.0,
+                cutoff: 2.5,
+            }),
+            skin,
+        );
+        world
+    }
+
+    #[test]
+    fn lennard_jones_md_conserves_total_energy() {
+        let mut world = lennard_jones_crystal(3.0);
+        let e0 = world.kinetic_energy() + world.potential_energy();
+        assert!(
+            world.potential_energy() < -50.0 * 1000.0,
+            "crystal is not bound"
+        );
+
+        let mut worst = 0.0_f32;
+        for _ in 0..600 {
+            world.update(1.0 / 60.0);
+            let e = world.kinetic_energy() + world.potential_energy();
+            worst = worst.max(((e - e0) / e0).abs());
+        }
+        assert!(worst < 1e-3, "relative energy drift {worst}");
+    }
+
+    #[test]
+    fn neighbour_list_matches_all_pairs() {
+        // A skin larger than the box keeps every pair in the list forever
+        let mut listed = lennard_jones_crystal(3.0);
+        let mut all_pairs = lennard_jones_crystal(1e4);
+
+        for _ in 0..600 {
+            listed.update(1.0 / 60.0);
+            all_pairs.update(1.0 / 60.0);
+        }
+
+        for (a, b) in listed.balls.iter().zip(&all_pairs.balls) {
+            assert_eq!(a.pos, b.pos);
+            assert_eq!(a.vel, b.vel);
+        }
+    }
+
+    #[test]
+    fn thermalize_sets_exact_energy_and_zero_momentum() {
+        let mut world = granular_gas(400, 2000.0, 8.0, 1.0);
+        // Two species, the heavy one 16 times the mass of the light one
+        for ball in world.balls.iter_mut().step_by(2) {
+            ball.mass *= 16.0;
+        }
+        let kt = 50.0;
+        world.thermalize(kt, &mut StdRng::seed_from_u64(3));
+
+        let n = world.ball_count() as f32;
+        assert!((world.kinetic_energy() - (n - 1.0) * kt).abs() < 1e-4 * n * kt);
+        let momentum: Vec2 = world.balls.iter().map(|b| b.vel * b.mass).sum();
+        let typical = world
+            .balls
+            .iter()
+            .map(|b| b.mass * b.vel.length())
+            .sum::<f32>()
+            / n;
+        assert!(momentum.length() < 1e-3 * typical);
+
+        // Equipartition between species: light balls move 4 times faster but
+        // carry the same mean kinetic energy
+        let mean_energy = |parity: usize| {
+            world
+                .balls
+                .iter()
+                .skip(parity)
+                .step_by(2)
+                .map(Ball::kinetic_energy)
+                .sum::<f32>()
+                / (n / 2.0)
+        };
+        let (heavy, light) = (mean_energy(0), mean_energy(1));
+        assert!(
+            (heavy / light - 1.0).abs() < 0.2,
+            "heavy {heavy}, light {light}"
+        );
+        assert!((heavy + light - 2.0 * kt).abs() < 0.05 * kt);
+    }
+
+    #[test]
+    fn vacf_of_circular_orbit_is_cosine() {
+        // Uniform circular motion in a harmonic trap: v(0)·v(t) = v² cos ωt
+        // for every time origin
+        let (omega, orbit) = (2.0_f32, 100.0);
+        let center = Vec2::new(200.0, 200.0);
+        let ball = Ball::new(
+            center + Vec2::new(orbit, 0.0),
+            Vec2::new(0.0, omega * orbit),
+            5.0,
+            [1.0; 4],
+        );
+        let mut world = World::new(400.0, 400.0);
+        world.add_force(ExternalForce::Harmonic {
+            center,
+            stiffness: ball.mass * omega * omega,
+        });
+        world.add_ball(ball);
+        world.record_vacf(VelocityAutocorrelation::new(0.05, 100).with_origin_spacing(3));
+
+        for _ in 0..1200 {
+            world.update(1.0 / 60.0);
+        }
+
+        let vacf = world.vacf().unwrap();
+        // 20 s at 0.05 s per sample, give or take round-off in the last one
+        assert!((400..=401).contains(&vacf.samples()));
+        for (t, c) in vacf.values() {
+            assert!((c - (omega * t).cos()).abs() < 1e-2, "C({t}) = {c}");
         }
+        let window = vacf.lags() as f32 * vacf.interval();
+        let exact = 0.5 * (omega * orbit).powi(2) * (omega * window).sin() / omega;
+        let d = vacf.diffusion_coefficient();
+        assert!(
+            (d - exact).abs() < 0.02 * exact.abs(),
+            "D = {d}, exact {exact}"
+        );
+    }
+
+    #[test]
+    fn hard_disk_green_kubo_diffusion_matches_enskog() {
+        // 300 unit disks at area fraction 0.2 with kT / m = 100
+        let (count, phi) = (300, 0.2);
+        let size = (count as f32 * PI / phi).sqrt();
+        let mut world = granular_gas(count, size, 1.0, 1.0);
+        world.thermalize(100.0 * PI, &mut StdRng::seed_from_u64(5));
+        for _ in 0..60 {
+            world.update(1.0 / 60.0);
+        }
+        world.record_vacf(VelocityAutocorrelation::new(0.05, 30).with_origin_spacing(2));
+        for _ in 0..1800 {
+            world.update(1.0 / 60.0);
+        }
+
+        // Enskog: D = √(kT / π m) / (2 n σ g(σ)), g(σ) = (1 - 7φ/16) / (1 - φ)²
+        let density = count as f32 / (size * size);
+        let contact = (1.0 - 7.0 * phi / 16.0) / ((1.0 - phi) * (1.0 - phi));
+        let kt_over_m = world.kinetic_energy() / count as f32 / PI;
+        let enskog = (kt_over_m / PI).sqrt() / (2.0 * density * 2.0 * contact);
+
+        let vacf = world.vacf().unwrap();
+        let d = vacf.diffusion_coefficient();
+        // Velocities reversed at the walls pull D a few percent below the
+        // bulk value
+        assert!((d / enskog - 1.0).abs() < 0.15, "D = {d}, Enskog {enskog}");
+        let (_, c_end) = vacf.values()[vacf.lags()];
+        assert!(c_end.abs() < 0.05, "C(t) not decayed: {c_end}");
     }
 }
@@ -1,9 +1,17 @@
 //! Visualization for the elastic balls 2D simulation.
 
-use elastic_balls_2d::{Ball, World};
+use elastic_balls_2d::{
+    Ball, ExternalForce, Obstacle, PairPotential, VelocityAutocorrelation, World,
+};
 use macroquad::prelude::*;
 use ::rand::Rng;
 
+/// Velocity autocorrelation over a 5 s window, sampled every 20 ms with a new
+/// time origin every 0.1 s.
+fn new_vacf() -> VelocityAutocorrelation {
+    VelocityAutocorrelation::new(0.02, 250).with_origin_spacing(5)
+}
+
 fn random_ball(width: f32, height: f32) -> Ball {
     let mut rng = ::rand::thread_rng();
     let radius = rng.gen_range(10.0..40.0);
@@ -24,6 +32,44 @@ fn random_ball(width: f32, height: f32) -> Ball {
     Ball::new(pos, vel, radius, color)
 }
 
+/// A Galton board: a funnel feeding rows of pegs above collection bins.
+fn galton_board(width: f32, height: f32) -> Vec<Obstacle> {
+    let cx = width / 2.0;
+    let spacing = 100.0;
+    let mut obstacles = vec![
+        Obstacle::Segment {
+            a: Vec2::new(cx - width * 0.35, height * 0.05),
+            b: Vec2::new(cx - 45.0, height * 0.3),
+        },
+        Obstacle::Segment {
+            a: Vec2::new(cx + width * 0.35, height * 0.05),
+            b: Vec2::new(cx + 45.0, height * 0.3),
+        },
+    ];
+
+    for row in 0..4 {
+        let y = height * 0.4 + row as f32 * spacing * 0.8;
+        for k in 0..=row {
+            let x = cx + (k as f32 - row as f32 / 2.0) * spacing;
+            obstacles.push(Obstacle::Circle {
+                center: Vec2::new(x, y),
+                radius: 8.0,
+            });
+        }
+    }
+
+    let bins = (width / spacing) as i32 / 2;
+    for k in -bins..=bins {
+        let x = cx + (k as f32 + 0.5) * spacing;
+        obstacles.push(Obstacle::Segment {
+            a: Vec2::new(x, height * 0.8),
+            b: Vec2::new(x, height),
+        });
+    }
+
+    obstacles
+}
+
 #[macroquad::main("Elastic Balls 2D")]
 async fn main() {
     let mut world = World::new(screen_width(), screen_height());
@@ -52,6 +98,63 @@ async fn main() {
                 let ball = random_ball(world.width, world.height);
                 world.add_ball(ball);
             }
+            if world.vacf().is_some() {
+                world.record_vacf(new_vacf());
+            }
+        }
+
+        // V: start or stop measuring the velocity autocorrelation
+        if is_key_pressed(KeyCode::V) && world.take_vacf().is_none() {
+            world.record_vacf(new_vacf());
+        }
+
+        if is_key_pressed(KeyCode::O) {
+            if world.obstacles.is_empty() {
+                world.obstacles = galton_board(world.width, world.height);
+            } else {
+                world.obstacles.clear();
+            }
+        }
+
+        if is_key_pressed(KeyCode::G) {
+            if world.forces.is_empty() {
+                world.add_force(ExternalForce::Gravity(Vec2::new(0.0, 500.0)));
+            } else {
+                world.forces.clear();
+            }
+        }
+
+        if is_key_pressed(KeyCode::L) {
+            if world.pair_potential().is_none() {
+                // Well depth set by the current temperature, so the gas
+                // condenses once it is cooled a little
+                let epsilon = 2.0 * world.temperature().max(1.0);
+                let potential = PairPotential::LennardJones {
+                    epsilon,
+                    cutoff: 2.5,
+                };
+                world.set_pair_potential(Some(potential), 10.0);
+            } else {
+                world.set_pair_potential(None, 0.0);
+            }
+        }
+
+        // M: Maxwell–Boltzmann velocities at the current temperature
+        if is_key_pressed(KeyCode::M) && world.ball_count() > 1 {
+            let kt = world.kinetic_energy() / (world.ball_count() - 1) as f32;
+            world.thermalize(kt, &mut ::rand::thread_rng());
+        }
+
+        // [ / ]: cool / heat by rescaling all velocities
+        let scale = if is_key_pressed(KeyCode::LeftBracket) {
+            0.9
+        } else if is_key_pressed(KeyCode::RightBracket) {
+            1.1
+        } else {
+            1.0
+        };
+        for ball in world.balls.iter_mut() {
+            ball.vel *= scale;
         }
 
         if is_key_pressed(KeyCode::Up) {
@@ -73,6 +176,21 @@ async fn main() {
         // Boundary
         draw_rectangle_lines(0.0, 0.0, world.width, world.height, 2.0, WHITE);
 
+        // Obstacles
+        let obstacle_color = Color::new(0.6, 0.6, 0.65, 1.0);
+        for obstacle in &world.obstacles {
+            match obstacle {
+                Obstacle::Circle { center, radius } => {
+                    draw_circle(center.x, center.y, *radius, obstacle_color);
+                }
+                _ => {
+                    for (a, b) in obstacle.edges() {
+                        draw_line(a.x, a.y, b.x, b.y, 3.0, obstacle_color);
+                    }
+                }
+            }
+        }
+
         // Balls
         for ball in &world.balls {
             let c = Color::new(ball.color[0], ball.color[1], ball.color[2], ball.color[3]);
@@ -82,15 +200,33 @@ async fn main() {
 
         // HUD
         let hud = format!(
-            "Balls: {}  Speed: {:.1}x  FPS: {}{}",
+            "Balls: {}  Speed: {:.1}x  T: {:.0}{}  FPS: {}{}",
             world.ball_count(),
             world.speed_multiplier,
+            world.temperature(),
+            if world.pair_potential().is_some() {
+                format!("  LJ E: {:.0}", world.kinetic_energy() + world.potential_energy())
+            } else {
+                String::new()
+            },
             get_fps(),
             if world.paused { "  [PAUSED]" } else { "" },
         );
         draw_text(&hud, 10.0, 24.0, 20.0, WHITE);
+        if let Some(vacf) = world.vacf() {
+            let values = vacf.values();
+            let at = |t: f32| values[((t / vacf.interval()) as usize).min(vacf.lags())].1;
+            let text = format!(
+                "VACF  C(0.5s): {:.2}  C(2s): {:.2}  C(5s): {:.2}  Green-Kubo D: {:.0}",
+                at(0.5),
+                at(2.0),
+                at(5.0),
+                vacf.diffusion_coefficient(),
+            );
+            draw_text(&text, 10.0, 48.0, 20.0, WHITE);
+        }
         draw_text(
-            "Click: add ball | Space: pause | R: reset | Up/Down: speed",
+            "Click: add ball | Space: pause | R: reset | O: obstacles | G: gravity | L: Lennard-Jones | M: Maxwell-Boltzmann | V: VACF | [/]: cool/heat | Up/Down: speed",
             10.0,
             world.height - 10.0,
             16.0,
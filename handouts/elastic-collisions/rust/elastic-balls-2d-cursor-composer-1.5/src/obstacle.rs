@@ -0,0 +1,146 @@
+//! Static obstacles: line segments, polygons and fixed circles.
+
+use macroquad::math::Vec2;
+
+/// A static obstacle that balls bounce off.
+#[derive(Debug, Clone)]
+pub enum Obstacle {
+    /// A line segment from `a` to `b`; balls bounce off both sides and the
+    /// end points.
+    Segment { a: Vec2, b: Vec2 },
+    /// A solid closed polygon, convex or concave, with vertices listed in
+    /// order (either winding).
+    Polygon { vertices: Vec<Vec2> },
+    /// A fixed solid circle.
+    Circle { center: Vec2, radius: f32 },
+}
+
+/// A ball-obstacle contact: unit normal pointing from the obstacle towards
+/// the ball, and how far the ball has to move along it to just touch.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Contact {
+    pub normal: Vec2,
+    pub depth: f32,
+}
+
+impl Obstacle {
+    /// Convenience constructor for an axis-aligned rectangle polygon.
+    pub fn rectangle(min: Vec2, max: Vec2) -> Self {
+        Obstacle::Polygon {
+            vertices: vec![min, Vec2::new(max.x, min.y), max, Vec2::new(min.x, max.y)],
+        }
+    }
+
+    /// Edges of the obstacle as segments (empty for circles).
+    pub fn edges(&self) -> Vec<(Vec2, Vec2)> {
+        match self {
+            Obstacle::Segment { a, b } => vec![(*a, *b)],
+            Obstacle::Polygon { vertices } => (0..vertices.len())
+                .map(|k| (vertices[k], vertices[(k + 1) % vertices.len()]))
+                .collect(),
+            Obstacle::Circle { .. } => Vec::new(),
+        }
+    }
+
+    /// Deepest contact of a ball at `pos` moving with `vel` (used to break
+    /// ties when the centre lies exactly on the obstacle). A ball in a concave
+    /// corner may touch several edges; resolve and ask again.
+    pub(crate) fn contact(&self, pos: Vec2, vel: Vec2, radius: f32) -> Option<Contact> {
+        match self {
+            Obstacle::Circle {
+                center,
+                radius: obstacle_radius,
+            } => {
+                let d = pos - *center;
+                let dist = d.length();
+                let min_dist = radius + obstacle_radius;
+                if dist >= min_dist {
+                    return None;
+                }
+                let normal = if dist > 0.0 {
+                    d / dist
+                } else {
+                    -vel.normalize_or(Vec2::X)
+                };
+                Some(Contact {
+                    normal,
+                    depth: min_dist - dist,
+                })
+            }
+            Obstacle::Polygon { vertices } if point_in_polygon(pos, vertices) => {
+                // Centre inside the solid: push out through the nearest edge
+                let nearest = self
+                    .edges()
+                    .into_iter()
+                    .map(|(a, b)| closest_point_on_segment(pos, a, b))
+                    .min_by(|p, q| p.distance_squared(pos).total_cmp(&q.distance_squared(pos)))?;
+                let d = nearest - pos;
+                let dist = d.length();
+                let normal = if dist > 0.0 {
+                    d / dist
+                } else {
+                    -vel.normalize_or(Vec2::X)
+                };
+                Some(Contact {
+                    normal,
+                    depth: dist + radius,
+                })
+            }
+            _ => self
+                .edges()
+                .into_iter()
+                .filter_map(|(a, b)| segment_contact(pos, vel, radius, a, b))
+                .max_by(|p, q| p.depth.total_cmp(&q.depth)),
+        }
+    }
+}
+
+/// Contact with segment `ab` from either side. When the closest point is an
+/// end point the normal points away from that vertex.
+fn segment_contact(pos: Vec2, vel: Vec2, radius: f32, a: Vec2, b: Vec2) -> Option<Contact> {
+    let closest = closest_point_on_segment(pos, a, b);
+    let d = pos - closest;
+    let dist = d.length();
+    if dist >= radius {
+        return None;
+    }
+
+    let normal = if dist > 0.0 {
+        d / dist
+    } else {
+        // Centre exactly on the segment: bounce back the way it came
+        let n = (b - a).perp().normalize_or(Vec2::Y);
+        if n.dot(vel) > 0.0 {
+            -n
+        } else {
+            n
+        }
+    };
+    Some(Contact {
+        normal,
+        depth: radius - dist,
+    })
+}
+
+fn closest_point_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    let len_sq = ab.length_squared();
+    if len_sq == 0.0 {
+        return a;
+    }
+    let t = ((p - a).dot(ab) / len_sq).clamp(0.0, 1.0);
+    a + ab * t
+}
+
+/// Even-odd rule, so it works for concave polygons too.
+fn point_in_polygon(p: Vec2, vertices: &[Vec2]) -> bool {
+    let mut inside = false;
+    let n = vertices.len();
+    for k in 0..n {
+        let (a, b) = (vertices[k], vertices[(k + 1) % n]);
+        if (a.y > p.y) != (b.y > p.y) && p.x < a.x + (p.y - a.y) / (b.y - a.y) * (b.x - a.x) {
+            inside = !inside;
+        }
+    }
+    inside
+}
@@ -0,0 +1,176 @@
+//! Smooth pair potentials for molecular-dynamics mode.
+//!
+//! Every potential is written in terms of the contact distance
+//! `σ = r_a + r_b` of the pair, so balls of different sizes interact
+//! consistently and the drawn radii keep their meaning. Potentials are
+//! truncated at `cutoff × σ` and shifted so that the energy is continuous
+//! there.
+
+use crate::Ball;
+use macroquad::math::Vec2;
+use std::fmt;
+
+/// A smooth, isotropic interaction U(r) between two ball centres.
+pub enum PairPotential {
+    /// U = 4ε[(σ/r)¹² − (σ/r)⁶]; a cutoff of 2.5 is customary.
+    LennardJones { epsilon: f32, cutoff: f32 },
+    /// Weeks–Chandler–Andersen: Lennard-Jones cut at its minimum 2^(1/6) σ,
+    /// leaving a purely repulsive soft-disk potential.
+    Wca { epsilon: f32 },
+    /// Screened Coulomb U = ε (σ/r) exp(−κ (r/σ − 1)), with `epsilon` the
+    /// energy at contact and `screening` κ in units of 1/σ.
+    Yukawa {
+        epsilon: f32,
+        screening: f32,
+        cutoff: f32,
+    },
+    /// User-defined `f(r, σ) -> (U, dU/dr)`.
+    Custom {
+        cutoff: f32,
+        potential: Box<dyn Fn(f32, f32) -> (f32, f32)>,
+    },
+}
+
+impl PairPotential {
+    /// Wrap a closure returning `(U, dU/dr)` for separation `r` and contact
+    /// distance `σ`.
+    pub fn custom(cutoff: f32, potential: impl Fn(f32, f32) -> (f32, f32) + 'static) -> Self {
+        PairPotential::Custom {
+            cutoff,
+            potential: Box::new(potential),
+        }
+    }
+
+    /// Interaction range for a pair with contact distance `sigma`.
+    pub fn cutoff(&self, sigma: f32) -> f32 {
+        let cutoff = match self {
+            PairPotential::LennardJones { cutoff, .. }
+            | PairPotential::Yukawa { cutoff, .. }
+            | PairPotential::Custom { cutoff, .. } => *cutoff,
+            PairPotential::Wca { .. } => 2.0_f32.powf(1.0 / 6.0),
+        };
+        cutoff * sigma
+    }
+
+    /// Unshifted `(U, dU/dr)` at separation `r`.
+    fn raw(&self, r: f32, sigma: f32) -> (f32, f32) {
+        match self {
+            PairPotential::LennardJones { epsilon, .. } | PairPotential::Wca { epsilon } => {
+                let s6 = (sigma / r).powi(6);
+                let s12 = s6 * s6;
+                (
+                    4.0 * epsilon * (s12 - s6),
+                    -24.0 * epsilon * (2.0 * s12 - s6) / r,
+                )
+            }
+            PairPotential::Yukawa {
+                epsilon, screening, ..
+            } => {
+                let u = epsilon * sigma / r * (-screening * (r / sigma - 1.0)).exp();
+                (u, -u * (1.0 / r + screening / sigma))
+            }
+            PairPotential::Custom { potential, .. } => potential(r, sigma),
+        }
+    }
+
+    /// Truncated and shifted energy of a pair at separation `r`.
+    pub fn energy(&self, r: f32, sigma: f32) -> f32 {
+        let cutoff = self.cutoff(sigma);
+        if r >= cutoff {
+            return 0.0;
+        }
+        self.raw(r, sigma).0 - self.raw(cutoff, sigma).0
+    }
+
+    /// Force on `b` due to `a` (minus that on `a`), zero beyond the cutoff.
+    pub fn force(&self, a: &Ball, b: &Ball) -> Vec2 {
+        let d = b.pos - a.pos;
+        let r = d.length();
+        let sigma = a.radius + b.radius;
+        if r >= self.cutoff(sigma) || r == 0.0 {
+            return Vec2::ZERO;
+        }
+        d * (-self.raw(r, sigma).1 / r)
+    }
+}
+
+impl fmt::Debug for PairPotential {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PairPotential::LennardJones { epsilon, cutoff } => f
+                .debug_struct("LennardJones")
+                .field("epsilon", epsilon)
+                .field("cutoff", cutoff)
+                .finish(),
+            PairPotential::Wca { epsilon } => {
+                f.debug_struct("Wca").field("epsilon", epsilon).finish()
+            }
+            PairPotential::Yukawa {
+                epsilon,
+                screening,
+                cutoff,
+            } => f
+                .debug_struct("Yukawa")
+                .field("epsilon", epsilon)
+                .field("screening", screening)
+                .field("cutoff", cutoff)
+                .finish(),
+            PairPotential::Custom { cutoff, .. } => f
+                .debug_struct("Custom")
+                .field("cutoff", cutoff)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+/// Verlet neighbour list: every pair closer than its cutoff plus `skin`,
+/// rebuilt only once some ball has moved more than half the skin.
+#[derive(Debug, Default)]
+pub(crate) struct NeighbourList {
+    pairs: Vec<(usize, usize)>,
+    /// Positions at the last rebuild.
+    reference: Vec<Vec2>,
+    stale: bool,
+}
+
+impl NeighbourList {
+    /// Force a rebuild on the next update (balls added or removed, potential
+    /// or skin changed).
+    pub(crate) fn invalidate(&mut self) {
+        self.stale = true;
+    }
+
+    /// Pairs that may interact, rebuilt first if needed.
+    pub(crate) fn update(
+        &mut self,
+        balls: &[Ball],
+        potential: &PairPotential,
+        skin: f32,
+    ) -> &[(usize, usize)] {
+        let moved_too_far = || {
+            let limit = 0.5 * skin;
+            balls
+                .iter()
+                .zip(&self.reference)
+                .any(|(ball, r0)| ball.pos.distance_squared(*r0) > limit * limit)
+        };
+        if self.stale || self.reference.len() != balls.len() || moved_too_far() {
+            self.rebuild(balls, potential, skin);
+        }
+        &self.pairs
+    }
+
+    fn rebuild(&mut self, balls: &[Ball], potential: &PairPotential, skin: f32) {
+        self.pairs.clear();
+        for i in 0..balls.len() {
+            for j in (i + 1)..balls.len() {
+                let range = potential.cutoff(balls[i].radius + balls[j].radius) + skin;
+                if balls[i].pos.distance_squared(balls[j].pos) < range * range {
+                    self.pairs.push((i, j));
+                }
+            }
+        }
+        self.reference = balls.iter().map(|b| b.pos).collect();
+        self.stale = false;
+    }
+}
@@ -0,0 +1,202 @@
+//! Velocity autocorrelation function and Green–Kubo self-diffusion.
+
+use crate::{Ball, World};
+use macroquad::math::Vec2;
+use std::collections::VecDeque;
+
+/// Velocities of all balls at one time origin.
+#[derive(Debug, Clone)]
+struct Origin {
+    /// Index of the sample the velocities were taken at.
+    sample: usize,
+    velocities: Vec<Vec2>,
+}
+
+/// Velocity autocorrelation `C(t) = ⟨v(0)·v(t)⟩ / ⟨v²⟩`, averaged over all
+/// balls and over time origins spread through the run.
+///
+/// Attached with [`World::record_vacf`], it is sampled every `interval` of
+/// simulated time for lags `0, interval, …, lags · interval`. A new time
+/// origin is opened every `origin_spacing` samples and kept in a ring buffer
+/// until it falls out of the window, so each sample costs one dot product
+/// per ball and open origin: `lags / origin_spacing` origins in all, however
+/// long the run. Long windows, as needed to follow the `1/t` tail in 2D, stay
+/// cheap with a spacing of a few mean free times.
+///
+/// Balls are identified by their index; origins taken with a different
+/// number of balls are dropped. Walls reflect velocities, so in a closed box
+/// `C(t)` turns negative once balls cross it; keep the window shorter than
+/// that.
+#[derive(Debug, Clone)]
+pub struct VelocityAutocorrelation {
+    interval: f32,
+    origin_spacing: usize,
+    /// Simulated time left until the next sample.
+    until_sample: f32,
+    samples: usize,
+    origins: VecDeque<Origin>,
+    /// Summed `v(0)·v(t)` and number of terms, per lag.
+    sums: Vec<f64>,
+    counts: Vec<u64>,
+}
+
+impl VelocityAutocorrelation {
+    /// Sample every `interval` seconds out to `lags` intervals, opening a
+    /// time origin at every sample; see
+    /// [`VelocityAutocorrelation::with_origin_spacing`].
+    pub fn new(interval: f32, lags: usize) -> Self {
+        assert!(interval > 0.0, "VACF sampling interval must be positive");
+        Self {
+            interval,
+            origin_spacing: 1,
+            until_sample: 0.0,
+            samples: 0,
+            origins: VecDeque::new(),
+            sums: vec![0.0; lags + 1],
+            counts: vec![0; lags + 1],
+        }
+    }
+
+    /// Open a time origin only every `spacing` samples, dividing the cost per
+    /// sample by `spacing`.
+    pub fn with_origin_spacing(mut self, spacing: usize) -> Self {
+        self.origin_spacing = spacing.max(1);
+        self
+    }
+
+    /// Time between samples.
+    pub fn interval(&self) -> f32 {
+        self.interval
+    }
+
+    /// Number of lags after lag zero.
+    pub fn lags(&self) -> usize {
+        self.sums.len() - 1
+    }
+
+    /// Number of samples taken so far.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+
+    /// Time left until the next sample is due.
+    pub(crate) fn until_sample(&self) -> f32 {
+        self.until_sample
+    }
+
+    /// Count `dt` of simulated time and sample `balls` if that reaches the
+    /// next sample time.
+    pub(crate) fn advance(&mut self, dt: f32, balls: &[Ball]) {
+        self.until_sample -= dt;
+        if self.until_sample <= 0.0 {
+            self.record(balls);
+        }
+    }
+
+    /// Correlate the current velocities with every open origin.
+    pub(crate) fn record(&mut self, balls: &[Ball]) {
+        if self
+            .origins
+            .front()
+            .is_some_and(|o| o.velocities.len() != balls.len())
+        {
+            self.origins.clear();
+        }
+
+        // Close origins that have left the window, reusing the oldest buffer
+        let mut spare = None;
+        while let Some(origin) = self.origins.front() {
+            if self.samples - origin.sample <= self.lags() {
+                break;
+            }
+            spare = self.origins.pop_front().map(|o| o.velocities);
+        }
+        if self.samples.is_multiple_of(self.origin_spacing) {
+            let mut velocities = spare.unwrap_or_default();
+            velocities.clear();
+            velocities.extend(balls.iter().map(|b| b.vel));
+            self.origins.push_back(Origin {
+                sample: self.samples,
+                velocities,
+            });
+        }
+
+        for origin in &self.origins {
+            let lag = self.samples - origin.sample;
+            let sum: f32 = origin
+                .velocities
+                .iter()
+                .zip(balls)
+                .map(|(v0, ball)| v0.dot(ball.vel))
+                .sum();
+            self.sums[lag] += f64::from(sum);
+            self.counts[lag] += balls.len() as u64;
+        }
+
+        self.samples += 1;
+        self.until_sample = self.interval;
+    }
+
+    /// Unnormalised `⟨v(0)·v(t)⟩` per lag, zero where nothing was measured.
+    fn correlation(&self) -> Vec<f64> {
+        self.sums
+            .iter()
+            .zip(&self.counts)
+            .map(|(&sum, &count)| if count > 0 { sum / count as f64 } else { 0.0 })
+            .collect()
+    }
+
+    /// `(t, C(t))` for every lag, normalised so that `C(0) = 1`.
+    pub fn values(&self) -> Vec<(f32, f32)> {
+        let correlation = self.correlation();
+        let c0 = correlation[0];
+        correlation
+            .iter()
+            .enumerate()
+            .map(|(lag, &c)| {
+                let normalised = if c0 > 0.0 { c / c0 } else { 0.0 };
+                (lag as f32 * self.interval, normalised as f32)
+            })
+            .collect()
+    }
+
+    /// Running Green–Kubo integral `(t, D(t))` with
+    /// `D(t) = ½ ∫₀ᵗ ⟨v(0)·v(s)⟩ ds` in 2D (trapezoidal rule). With the
+    /// `1/t` tail, `D(t)` keeps creeping up logarithmically instead of
+    /// levelling off.
+    pub fn green_kubo(&self) -> Vec<(f32, f32)> {
+        let correlation = self.correlation();
+        let dt = f64::from(self.interval);
+        let mut integral = 0.0;
+        let mut running = vec![(0.0, 0.0)];
+        for (lag, pair) in correlation.windows(2).enumerate() {
+            integral += 0.5 * (pair[0] + pair[1]) * dt;
+            running.push(((lag + 1) as f32 * self.interval, (0.5 * integral) as f32));
+        }
+        running
+    }
+
+    /// Green–Kubo diffusion coefficient integrated over the whole window.
+    pub fn diffusion_coefficient(&self) -> f32 {
+        self.green_kubo().last().map_or(0.0, |&(_, d)| d)
+    }
+}
+
+impl World {
+    /// Start sampling the velocity autocorrelation into `vacf`, taking its
+    /// first sample now. Replaces any accumulator already attached.
+    pub fn record_vacf(&mut self, mut vacf: VelocityAutocorrelation) {
+        vacf.record(&self.balls);
+        self.vacf = Some(vacf);
+    }
+
+    /// The attached velocity autocorrelation, if any.
+    pub fn vacf(&self) -> Option<&VelocityAutocorrelation> {
+        self.vacf.as_ref()
+    }
+
+    /// Stop sampling and hand back the accumulator.
+    pub fn take_vacf(&mut self) -> Option<VelocityAutocorrelation> {
+        self.vacf.take()
+    }
+}
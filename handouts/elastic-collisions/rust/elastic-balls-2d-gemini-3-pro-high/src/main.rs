@@ -1,7 +1,16 @@
 use macroquad::prelude::*;
+use macroquad::rand::RandGenerator;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 mod sim;
-use sim::Ball;
+mod stats;
+use sim::{Ball, Container};
+use stats::{MaxwellBoltzmann, SpeedHistogram};
+
+const INITIAL_BALLS: usize = 15;
+const HISTOGRAM_BINS: usize = 24;
+// Frames of speeds shown in the histogram, a few seconds at 60 FPS
+const HISTOGRAM_FRAMES: usize = 180;
 
 fn window_conf() -> Conf {
     Conf {
@@ -12,17 +21,112 @@ fn window_conf() -> Conf {
     }
 }
 
+#[derive(Clone, Copy, PartialEq)]
+enum Shape {
+    Rectangle,
+    Circle,
+    Ellipse,
+}
+
+impl Shape {
+    fn next(self) -> Self {
+        match self {
+            Shape::Rectangle => Shape::Circle,
+            Shape::Circle => Shape::Ellipse,
+            Shape::Ellipse => Shape::Rectangle,
+        }
+    }
+}
+
+// Largest container of the given shape that fits the window, with a small margin
+fn container_for(shape: Shape, width: f32, height: f32) -> Container {
+    let margin = 10.0;
+    let center = vec2(width, height) / 2.0;
+    match shape {
+        Shape::Rectangle => Container::Rectangle {
+            min: Vec2::splat(margin),
+            max: vec2(width, height) - margin,
+        },
+        Shape::Circle => Container::Circle {
+            center,
+            radius: center.min_element() - margin,
+        },
+        Shape::Ellipse => Container::Ellipse {
+            center,
+            semi_axes: center - margin,
+        },
+    }
+}
+
+// Seed from `--seed N` in `args` or else from `env`, the ELASTIC_SEED
+// environment variable, so that a run can be replayed exactly. Errors if
+// `--seed` has no value or a value is not a non-negative integer.
+fn parse_seed(args: &[String], env: Option<&str>) -> Result<Option<u64>, String> {
+    let parse = |value: &str, source: &str| {
+        value
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("{source} must be a non-negative integer, got `{value}`"))
+    };
+    match args.iter().position(|arg| arg == "--seed") {
+        Some(i) => match args.get(i + 1) {
+            Some(value) => parse(value, "--seed"),
+            None => Err("--seed needs a value".to_string()),
+        },
+        None => env.map_or(Ok(None), |value| parse(value, "ELASTIC_SEED")),
+    }
+}
+
+// Seed requested for this run, exiting with a usage message if it is malformed
+fn seed_from_args() -> Option<u64> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let env = std::env::var("ELASTIC_SEED").ok();
+    parse_seed(&args, env.as_deref()).unwrap_or_else(|message| {
+        eprintln!("error: {message}");
+        eprintln!("usage: elastic-balls-2d-gemini-3-pro-high [--seed N]");
+        std::process::exit(2);
+    })
+}
+
+// Fresh seed from the clock, small enough to type back in
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos() as u64)
+}
+
+// Restart the generator from `seed` and respawn the initial balls
+fn reset(balls: &mut Vec<Ball>, container: &Container, rng: &RandGenerator, seed: u64) {
+    rng.srand(seed);
+    balls.clear();
+    for _ in 0..INITIAL_BALLS {
+        balls.push(Ball::random(container, rng));
+    }
+}
+
+// Empty histogram whose range suits the current temperature. Started afresh
+// whenever balls are added or reset, since that changes the equilibrium.
+fn speed_histogram(balls: &[Ball]) -> SpeedHistogram {
+    let range = MaxwellBoltzmann::from_balls(balls).speed_range();
+    SpeedHistogram::new(HISTOGRAM_BINS, range, HISTOGRAM_FRAMES)
+}
+
 #[macroquad::main(window_conf)]
 async fn main() {
     let mut balls = Vec::new();
+    let mut broad_phase = sim::SweepAndPrune::new();
     let mut click_start: Option<Vec2> = None;
     let mut show_trails = false;
     let mut paused = false;
+    let mut shape = Shape::Rectangle;
+    let mut show_histogram = true;
 
     // Create some initial random balls
-    for _ in 0..15 {
-        balls.push(Ball::random(screen_width(), screen_height()));
-    }
+    let mut seed = seed_from_args().unwrap_or_else(clock_seed);
+    let rng = RandGenerator::new();
+    let container = container_for(shape, screen_width(), screen_height());
+    reset(&mut balls, &container, &rng, seed);
+    let mut histogram = speed_histogram(&balls);
 
     loop {
         if !show_trails {
@@ -34,28 +138,36 @@ async fn main() {
 
         let dt = get_frame_time();
         let (width, height) = (screen_width(), screen_height());
+        let container = container_for(shape, width, height);
 
         if !paused {
             // Update positions
             for ball in &mut balls {
-                ball.update(dt, width, height);
+                ball.update(dt, &container);
             }
 
             // Handle collisions
-            for i in 0..balls.len() {
-                let (left, right) = balls.split_at_mut(i + 1);
-                let b1 = &mut left[i];
-                for b2 in right {
-                    sim::resolve_collision(b1, b2);
-                }
+            for &(i, j) in broad_phase.update(&balls) {
+                let (b1, b2) = sim::pair_mut(&mut balls, i, j);
+                sim::resolve_collision(b1, b2);
             }
+
+            histogram.record(&balls);
         }
 
+        container.draw(2.0, GRAY);
+
         // Draw balls
         for ball in &balls {
             ball.draw();
         }
 
+        // Speed distribution against Maxwell-Boltzmann at the current temperature
+        if show_histogram {
+            let theory = MaxwellBoltzmann::from_balls(&balls);
+            histogram.draw(&theory, vec2(width - 330.0, 10.0), vec2(320.0, 180.0));
+        }
+
         // Mouse interaction for spawning
         if is_mouse_button_pressed(MouseButton::Left) {
             click_start = Some(mouse_position().into());
@@ -67,9 +179,9 @@ async fn main() {
 
             if is_mouse_button_released(MouseButton::Left) {
                 let velocity = (start - current) * 2.0; // Velocity based on drag distance
-                let radius = rand::gen_range(10.0, 30.0);
-                let color = Color::new(rand::gen_range(0.5, 1.0), rand::gen_range(0.5, 1.0), rand::gen_range(0.5, 1.0), 1.0);
-                balls.push(Ball::new(start, velocity, radius, color));
+                let radius = rng.gen_range(10.0, 30.0);
+                balls.push(Ball::new(start, velocity, radius, sim::random_color(&rng)));
+                histogram = speed_histogram(&balls);
                 click_start = None;
             }
         }
@@ -77,22 +189,31 @@ async fn main() {
         // UI
         draw_text(&format!("FPS: {}", get_fps()), 10.0, 20.0, 20.0, WHITE);
         draw_text(&format!("Balls: {}", balls.len()), 10.0, 40.0, 20.0, WHITE);
-        draw_text("Controls:", 10.0, 70.0, 18.0, LIGHTGRAY);
-        draw_text("- Left Click & Drag: Spawn ball with velocity", 20.0, 90.0, 16.0, LIGHTGRAY);
-        draw_text("- SPACE: Add random ball", 20.0, 110.0, 16.0, LIGHTGRAY);
-        draw_text("- R: Reset", 20.0, 130.0, 16.0, LIGHTGRAY);
-        draw_text("- P: Pause/Resume", 20.0, 150.0, 16.0, LIGHTGRAY);
-        draw_text("- T: Toggle Trails", 20.0, 170.0, 16.0, LIGHTGRAY);
+        draw_text(&format!("Seed: {}", seed), 10.0, 60.0, 20.0, WHITE);
+        draw_text("Controls:", 10.0, 90.0, 18.0, LIGHTGRAY);
+        draw_text("- Left Click & Drag: Spawn ball with velocity", 20.0, 110.0, 16.0, LIGHTGRAY);
+        draw_text("- SPACE: Add random ball", 20.0, 130.0, 16.0, LIGHTGRAY);
+        draw_text("- R: Reset (same seed)", 20.0, 150.0, 16.0, LIGHTGRAY);
+        draw_text("- N: Reset with a new seed", 20.0, 170.0, 16.0, LIGHTGRAY);
+        draw_text("- P: Pause/Resume", 20.0, 190.0, 16.0, LIGHTGRAY);
+        draw_text("- T: Toggle Trails", 20.0, 210.0, 16.0, LIGHTGRAY);
+        draw_text("- C: Cycle Container Shape", 20.0, 230.0, 16.0, LIGHTGRAY);
+        draw_text("- H: Toggle Speed Histogram", 20.0, 250.0, 16.0, LIGHTGRAY);
 
         if is_key_pressed(KeyCode::Space) {
-            balls.push(Ball::random(width, height));
+            balls.push(Ball::random(&container, &rng));
+            histogram = speed_histogram(&balls);
         }
 
         if is_key_pressed(KeyCode::R) {
-            balls.clear();
-            for _ in 0..15 {
-                balls.push(Ball::random(width, height));
-            }
+            reset(&mut balls, &container, &rng, seed);
+            histogram = speed_histogram(&balls);
+        }
+
+        if is_key_pressed(KeyCode::N) {
+            seed = clock_seed();
+            reset(&mut balls, &container, &rng, seed);
+            histogram = speed_histogram(&balls);
         }
 
         if is_key_pressed(KeyCode::P) {
@@ -103,6 +224,38 @@ async fn main() {
             show_trails = !show_trails;
         }
 
+        if is_key_pressed(KeyCode::H) {
+            show_histogram = !show_histogram;
+        }
+
+        if is_key_pressed(KeyCode::C) {
+            // Balls outside the new container get pushed back in by the next update
+            shape = shape.next();
+        }
+
         next_frame().await
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_seed_from_flag_then_environment() {
+        assert_eq!(parse_seed(&args(&["--seed", "42"]), Some("7")), Ok(Some(42)));
+        assert_eq!(parse_seed(&args(&[]), Some("7")), Ok(Some(7)));
+        assert_eq!(parse_seed(&args(&[]), None), Ok(None));
+    }
+
+    #[test]
+    fn test_malformed_seed_is_an_error() {
+        assert!(parse_seed(&args(&["--seed"]), Some("7")).is_err());
+        assert!(parse_seed(&args(&["--seed", "1e3"]), None).is_err());
+        assert!(parse_seed(&args(&[]), Some("-1")).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use macroquad::prelude::*;
+use macroquad::rand::RandGenerator;
 
 #[derive(Clone, Copy, Debug)]
 pub struct Ball {
@@ -23,50 +24,203 @@ impl Ball {
         }
     }
 
-    pub fn random(screen_width: f32, screen_height: f32) -> Self {
-        let radius = rand::gen_range(10.0, 30.0);
-        let x = rand::gen_range(radius, screen_width - radius);
-        let y = rand::gen_range(radius, screen_height - radius);
-        let position = vec2(x, y);
+    // Random ball inside the container. Drawing everything from `rng` means a
+    // generator seeded with the same value always produces the same balls.
+    pub fn random(container: &Container, rng: &RandGenerator) -> Self {
+        let radius = rng.gen_range(10.0, 30.0);
+        let position = container.random_position(radius, rng);
 
         // Random velocity
-        let speed = rand::gen_range(50.0, 150.0);
-        let angle = rand::gen_range(0.0, std::f32::consts::TAU);
+        let speed = rng.gen_range(50.0, 150.0);
+        let angle = rng.gen_range(0.0, std::f32::consts::TAU);
         let velocity = vec2(angle.cos() * speed, angle.sin() * speed);
 
-        let color = Color::new(
-            rand::gen_range(0.5, 1.0),
-            rand::gen_range(0.5, 1.0),
-            rand::gen_range(0.5, 1.0),
-            1.0,
-        );
+        let color = random_color(rng);
 
         Self::new(position, velocity, radius, color)
     }
 
-    pub fn update(&mut self, dt: f32, screen_width: f32, screen_height: f32) {
+    pub fn update(&mut self, dt: f32, container: &Container) {
         self.position += self.velocity * dt;
+        container.confine(self);
+    }
+
+    pub fn draw(&self) {
+        draw_circle(self.position.x, self.position.y, self.radius, self.color);
+    }
+}
+
+// Bright random color for a new ball
+pub fn random_color(rng: &RandGenerator) -> Color {
+    Color::new(
+        rng.gen_range(0.5, 1.0),
+        rng.gen_range(0.5, 1.0),
+        rng.gen_range(0.5, 1.0),
+        1.0,
+    )
+}
+
+// The region the balls live in. Balls are reflected about the local surface
+// normal whenever they touch the boundary.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Container {
+    Rectangle { min: Vec2, max: Vec2 },
+    Circle { center: Vec2, radius: f32 },
+    // Axis-aligned ellipse with semi-axes `semi_axes.x` and `semi_axes.y`
+    Ellipse { center: Vec2, semi_axes: Vec2 },
+}
 
-        // Wall collisions
-        if self.position.x - self.radius < 0.0 {
-            self.position.x = self.radius;
-            self.velocity.x *= -1.0;
-        } else if self.position.x + self.radius > screen_width {
-            self.position.x = screen_width - self.radius;
-            self.velocity.x *= -1.0;
+impl Container {
+    // Keep the ball inside: push it back onto the boundary and mirror the
+    // normal component of its velocity if it is moving outwards.
+    pub fn confine(&self, ball: &mut Ball) {
+        match *self {
+            Container::Rectangle { min, max } => {
+                if ball.position.x - ball.radius < min.x {
+                    ball.position.x = min.x + ball.radius;
+                    reflect(&mut ball.velocity, -Vec2::X);
+                } else if ball.position.x + ball.radius > max.x {
+                    ball.position.x = max.x - ball.radius;
+                    reflect(&mut ball.velocity, Vec2::X);
+                }
+
+                if ball.position.y - ball.radius < min.y {
+                    ball.position.y = min.y + ball.radius;
+                    reflect(&mut ball.velocity, -Vec2::Y);
+                } else if ball.position.y + ball.radius > max.y {
+                    ball.position.y = max.y - ball.radius;
+                    reflect(&mut ball.velocity, Vec2::Y);
+                }
+            }
+            Container::Circle { center, radius } => {
+                let offset = ball.position - center;
+                let limit = (radius - ball.radius).max(0.0);
+                if offset.length_squared() <= limit * limit {
+                    return;
+                }
+                let normal = offset.normalize_or(-ball.velocity.normalize_or(Vec2::X));
+                ball.position = center + normal * limit;
+                reflect(&mut ball.velocity, normal);
+            }
+            Container::Ellipse { center, semi_axes } => {
+                let local = ball.position - center;
+                let outside = (local / semi_axes).length_squared() > 1.0;
+                let closest = closest_point_on_ellipse(local, semi_axes);
+                if !outside && closest.distance_squared(local) >= ball.radius * ball.radius {
+                    return;
+                }
+                // Outward normal of x^2/a^2 + y^2/b^2 = 1 at the contact point
+                let normal = (closest / (semi_axes * semi_axes)).normalize_or(Vec2::X);
+                ball.position = center + closest - normal * ball.radius;
+                reflect(&mut ball.velocity, normal);
+            }
         }
+    }
 
-        if self.position.y - self.radius < 0.0 {
-            self.position.y = self.radius;
-            self.velocity.y *= -1.0;
-        } else if self.position.y + self.radius > screen_height {
-            self.position.y = screen_height - self.radius;
-            self.velocity.y *= -1.0;
+    // Whether a ball of the given radius fits at `position` without touching
+    // the boundary.
+    pub fn contains(&self, position: Vec2, radius: f32) -> bool {
+        match *self {
+            Container::Rectangle { min, max } => {
+                position.cmpge(min + radius).all() && position.cmple(max - radius).all()
+            }
+            Container::Circle {
+                center,
+                radius: outer,
+            } => position.distance(center) <= outer - radius,
+            Container::Ellipse { center, semi_axes } => {
+                let local = position - center;
+                (local / semi_axes).length_squared() <= 1.0
+                    && closest_point_on_ellipse(local, semi_axes).distance(local) >= radius
+            }
         }
     }
 
-    pub fn draw(&self) {
-        draw_circle(self.position.x, self.position.y, self.radius, self.color);
+    // Axis-aligned bounding box as (min, max)
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        match *self {
+            Container::Rectangle { min, max } => (min, max),
+            Container::Circle { center, radius } => (center - radius, center + radius),
+            Container::Ellipse { center, semi_axes } => (center - semi_axes, center + semi_axes),
+        }
+    }
+
+    // Uniformly random position where a ball of the given radius fits, found
+    // by rejection sampling from the bounding box.
+    pub fn random_position(&self, radius: f32, rng: &RandGenerator) -> Vec2 {
+        let (min, max) = self.bounds();
+        for _ in 0..1000 {
+            let position = vec2(rng.gen_range(min.x, max.x), rng.gen_range(min.y, max.y));
+            if self.contains(position, radius) {
+                return position;
+            }
+        }
+        // Ball is (nearly) too big for the container
+        (min + max) / 2.0
+    }
+
+    pub fn draw(&self, thickness: f32, color: Color) {
+        match *self {
+            Container::Rectangle { min, max } => {
+                let size = max - min;
+                draw_rectangle_lines(min.x, min.y, size.x, size.y, thickness, color);
+            }
+            Container::Circle { center, radius } => {
+                draw_ellipse_outline(center, vec2(radius, radius), thickness, color)
+            }
+            Container::Ellipse { center, semi_axes } => {
+                draw_ellipse_outline(center, semi_axes, thickness, color)
+            }
+        }
+    }
+}
+
+fn reflect(velocity: &mut Vec2, normal: Vec2) {
+    let v_n = velocity.dot(normal);
+    if v_n > 0.0 {
+        *velocity -= 2.0 * v_n * normal;
+    }
+}
+
+// Closest point on the ellipse x^2/a^2 + y^2/b^2 = 1 to `p`, for points both
+// inside and outside. Iterates on the local circle of curvature, which
+// converges in a handful of steps without any trigonometry.
+fn closest_point_on_ellipse(p: Vec2, semi_axes: Vec2) -> Vec2 {
+    let (a, b) = (semi_axes.x, semi_axes.y);
+    let q = p.abs();
+    let mut t = Vec2::splat(std::f32::consts::FRAC_1_SQRT_2);
+
+    for _ in 0..4 {
+        let point = semi_axes * t;
+        // Centre of curvature (evolute) for the current estimate
+        let evolute = vec2(
+            (a * a - b * b) * t.x.powi(3) / a,
+            (b * b - a * a) * t.y.powi(3) / b,
+        );
+        let r = (point - evolute).length();
+        let d = q - evolute;
+        let len = d.length();
+        if len == 0.0 {
+            break;
+        }
+        t = ((d * (r / len) + evolute) / semi_axes).clamp(Vec2::ZERO, Vec2::ONE);
+        t = t.normalize_or(Vec2::X);
+    }
+
+    semi_axes * t * vec2(p.x.signum(), p.y.signum())
+}
+
+// The built-in ellipse outline uses only 20 sides, which is coarse enough for
+// balls to visibly cross it in a large container.
+fn draw_ellipse_outline(center: Vec2, semi_axes: Vec2, thickness: f32, color: Color) {
+    const SIDES: usize = 128;
+    let point = |k: usize| {
+        let angle = k as f32 / SIDES as f32 * std::f32::consts::TAU;
+        center + semi_axes * vec2(angle.cos(), angle.sin())
+    };
+    for k in 0..SIDES {
+        let (p0, p1) = (point(k), point(k + 1));
+        draw_line(p0.x, p0.y, p1.x, p1.y, thickness, color);
     }
 }
 
@@ -110,6 +264,65 @@ pub fn resolve_collision(b1: &mut Ball, b2: &mut Ball) {
     }
 }
 
+// Sweep-and-prune broad phase along the x axis. The sort order is kept
+// between frames, so the insertion sort only has to fix up the few balls that
+// swapped places since the last call.
+#[derive(Default)]
+pub struct SweepAndPrune {
+    order: Vec<usize>,
+    pairs: Vec<(usize, usize)>,
+}
+
+impl SweepAndPrune {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns index pairs (i < j) whose bounding boxes overlap.
+    pub fn update(&mut self, balls: &[Ball]) -> &[(usize, usize)] {
+        if self.order.len() > balls.len() {
+            // Balls were removed (e.g. on reset), indices are no longer valid
+            self.order.clear();
+        }
+        // Newly spawned balls go to the end and get sorted into place below
+        self.order.extend(self.order.len()..balls.len());
+
+        let min_x = |i: usize| balls[i].position.x - balls[i].radius;
+        for k in 1..self.order.len() {
+            let idx = self.order[k];
+            let key = min_x(idx);
+            let mut m = k;
+            while m > 0 && min_x(self.order[m - 1]) > key {
+                self.order[m] = self.order[m - 1];
+                m -= 1;
+            }
+            self.order[m] = idx;
+        }
+
+        self.pairs.clear();
+        for (k, &i) in self.order.iter().enumerate() {
+            let a = &balls[i];
+            let max_x = a.position.x + a.radius;
+            for &j in &self.order[k + 1..] {
+                let b = &balls[j];
+                if b.position.x - b.radius > max_x {
+                    break;
+                }
+                if (a.position.y - b.position.y).abs() <= a.radius + b.radius {
+                    self.pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+        &self.pairs
+    }
+}
+
+pub fn pair_mut(balls: &mut [Ball], i: usize, j: usize) -> (&mut Ball, &mut Ball) {
+    assert!(i < j);
+    let (left, right) = balls.split_at_mut(j);
+    (&mut left[i], &mut right[0])
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,4 +348,160 @@ mod tests {
         assert!(b1.velocity.x < 0.0);
         assert!(b2.velocity.x > 0.0, "b2 should move right");
     }
+
+    fn brute_force_pairs(balls: &[Ball]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        for i in 0..balls.len() {
+            for j in (i + 1)..balls.len() {
+                let d = balls[i].position - balls[j].position;
+                let r = balls[i].radius + balls[j].radius;
+                if d.length_squared() < r * r {
+                    pairs.push((i, j));
+                }
+            }
+        }
+        pairs
+    }
+
+    fn grid_of_balls(count: usize) -> Vec<Ball> {
+        (0..count)
+            .map(|k| {
+                let x = (k % 10) as f32 * 25.0 + (k * 7 % 11) as f32;
+                let y = (k / 10) as f32 * 25.0 + (k * 5 % 13) as f32;
+                let v = vec2((k % 7) as f32 - 3.0, (k % 5) as f32 - 2.0) * 20.0;
+                Ball::new(vec2(x, y), v, 8.0 + (k % 4) as f32, RED)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_sweep_and_prune_finds_all_overlaps() {
+        let mut balls = grid_of_balls(60);
+        let mut sap = SweepAndPrune::new();
+        let container = Container::Rectangle {
+            min: Vec2::ZERO,
+            max: vec2(300.0, 200.0),
+        };
+
+        for _ in 0..100 {
+            let mut candidates = sap.update(&balls).to_vec();
+            candidates.sort();
+            for pair in brute_force_pairs(&balls) {
+                assert!(candidates.binary_search(&pair).is_ok(), "missed {pair:?}");
+            }
+
+            for ball in &mut balls {
+                ball.update(0.05, &container);
+            }
+        }
+    }
+
+    #[test]
+    fn test_sweep_and_prune_handles_spawned_balls() {
+        let mut balls = grid_of_balls(20);
+        let mut sap = SweepAndPrune::new();
+        sap.update(&balls);
+
+        // Spawn a ball right on top of ball 0
+        balls.push(Ball::new(balls[0].position, Vec2::ZERO, 10.0, BLUE));
+        let pairs = sap.update(&balls).to_vec();
+        assert!(pairs.contains(&(0, 20)));
+
+        balls.truncate(5);
+        let pairs = sap.update(&balls).to_vec();
+        assert!(pairs.iter().all(|&(i, j)| i < j && j < 5));
+    }
+
+    #[test]
+    fn test_circle_container_reflects_about_normal() {
+        let container = Container::Circle {
+            center: vec2(100.0, 100.0),
+            radius: 50.0,
+        };
+        // Ball just past the boundary along the diagonal, moving outwards
+        // at an angle to the normal
+        let normal = vec2(1.0, 1.0).normalize();
+        let velocity = vec2(30.0, 10.0);
+        let mut ball = Ball::new(vec2(100.0, 100.0) + normal * 41.0, velocity, 10.0, RED);
+
+        container.confine(&mut ball);
+
+        assert!(container.contains(ball.position, ball.radius - 1e-3));
+        assert!((ball.velocity.length() - velocity.length()).abs() < 1e-4);
+        assert!((ball.velocity.dot(normal) + velocity.dot(normal)).abs() < 1e-4);
+        assert!((ball.velocity.perp_dot(normal) - velocity.perp_dot(normal)).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_rectangle_container_only_reflects_outward_motion() {
+        let container = Container::Rectangle {
+            min: vec2(0.0, 0.0),
+            max: vec2(100.0, 100.0),
+        };
+
+        // Overlapping the left wall but already moving back inside
+        let mut ball = Ball::new(vec2(5.0, 50.0), vec2(20.0, -5.0), 10.0, RED);
+        container.confine(&mut ball);
+        assert_eq!(ball.position.x, 10.0);
+        assert_eq!(ball.velocity, vec2(20.0, -5.0));
+
+        // Overlapping the bottom wall and moving outwards
+        let mut ball = Ball::new(vec2(50.0, 95.0), vec2(3.0, 40.0), 10.0, RED);
+        container.confine(&mut ball);
+        assert_eq!(ball.position.y, 90.0);
+        assert_eq!(ball.velocity, vec2(3.0, -40.0));
+    }
+
+    #[test]
+    fn test_ellipse_container_keeps_balls_inside() {
+        let container = Container::Ellipse {
+            center: vec2(200.0, 150.0),
+            semi_axes: vec2(180.0, 90.0),
+        };
+
+        // Head-on at the end of the major axis the normal is the x axis
+        let mut ball = Ball::new(vec2(375.0, 150.0), vec2(40.0, 0.0), 10.0, RED);
+        container.confine(&mut ball);
+        assert!((ball.position.x - 370.0).abs() < 1e-3);
+        assert!(ball.velocity.abs_diff_eq(vec2(-40.0, 0.0), 1e-4));
+
+        let mut balls: Vec<Ball> = grid_of_balls(20)
+            .into_iter()
+            .map(|mut b| {
+                b.position = container.bounds().0 + vec2(90.0, 60.0) + b.position * 0.4;
+                b
+            })
+            .collect();
+        let speeds: Vec<f32> = balls.iter().map(|b| b.velocity.length()).collect();
+        for _ in 0..2000 {
+            for ball in &mut balls {
+                ball.update(0.05, &container);
+                assert!(container.contains(ball.position, ball.radius - 1e-2));
+            }
+        }
+        for (ball, speed) in balls.iter().zip(speeds) {
+            assert!((ball.velocity.length() - speed).abs() < 1e-3 * speed.max(1.0));
+        }
+    }
+
+    #[test]
+    fn test_random_balls_repeat_for_same_seed() {
+        let container = Container::Circle {
+            center: vec2(200.0, 200.0),
+            radius: 150.0,
+        };
+        let spawn = |seed: u64| {
+            let rng = RandGenerator::new();
+            rng.srand(seed);
+            (0..10)
+                .map(|_| {
+                    let b = Ball::random(&container, &rng);
+                    (b.position, b.velocity, b.radius, b.color)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(spawn(42), spawn(42));
+        assert_ne!(spawn(42), spawn(43));
+    }
 }
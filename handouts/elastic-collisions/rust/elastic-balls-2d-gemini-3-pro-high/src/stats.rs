@@ -0,0 +1,324 @@
+use macroquad::prelude::*;
+use std::collections::VecDeque;
+
+use crate::sim::Ball;
+
+// Equilibrium speed distribution of a 2D gas at the temperature of the given
+// balls (k_B = 1). Balls of mass m have the Rayleigh distribution
+//   f_m(v) = (m v / kT) exp(-m v^2 / 2kT),
+// and since the balls come in different sizes the pooled distribution is the
+// average of f_m over all balls.
+#[derive(Clone, Debug, Default)]
+pub struct MaxwellBoltzmann {
+    pub kt: f32,
+    masses: Vec<f32>,
+}
+
+impl MaxwellBoltzmann {
+    // Temperature from equipartition, kT = E / N for two degrees of freedom
+    pub fn from_balls(balls: &[Ball]) -> Self {
+        let energy: f32 = balls
+            .iter()
+            .map(|b| 0.5 * b.mass * b.velocity.length_squared())
+            .sum();
+        Self {
+            kt: energy / balls.len().max(1) as f32,
+            masses: balls.iter().map(|b| b.mass).collect(),
+        }
+    }
+
+    pub fn pdf(&self, speed: f32) -> f32 {
+        if self.masses.is_empty() || self.kt <= 0.0 {
+            return 0.0;
+        }
+        let sum: f32 = self
+            .masses
+            .iter()
+            .map(|&m| m * speed / self.kt * (-m * speed * speed / (2.0 * self.kt)).exp())
+            .sum();
+        sum / self.masses.len() as f32
+    }
+
+    // Fraction of balls slower than `speed`
+    pub fn cdf(&self, speed: f32) -> f32 {
+        if self.masses.is_empty() || self.kt <= 0.0 {
+            return 0.0;
+        }
+        let tail: f32 = self
+            .masses
+            .iter()
+            .map(|&m| (-m * speed * speed / (2.0 * self.kt)).exp())
+            .sum();
+        1.0 - tail / self.masses.len() as f32
+    }
+
+    // Speed below which all but about 3 in 10,000 of the lightest balls lie,
+    // a good upper end for a histogram.
+    pub fn speed_range(&self) -> f32 {
+        let lightest = self.masses.iter().copied().fold(f32::INFINITY, f32::min);
+        if !lightest.is_finite() || self.kt <= 0.0 {
+            return 1.0;
+        }
+        4.0 * (self.kt / lightest).sqrt()
+    }
+}
+
+// Histogram of ball speeds over the last `window` recorded frames, so that it
+// follows the relaxation of the gas instead of remembering the initial state
+// forever. Speeds beyond the last bin are counted as overflow.
+pub struct SpeedHistogram {
+    max_speed: f32,
+    window: usize,
+    frames: VecDeque<Vec<u32>>,
+    // Running totals over `frames`, with the overflow in the last slot
+    counts: Vec<u32>,
+}
+
+impl SpeedHistogram {
+    pub fn new(bins: usize, max_speed: f32, window: usize) -> Self {
+        Self {
+            max_speed,
+            window: window.max(1),
+            frames: VecDeque::new(),
+            counts: vec![0; bins.max(1) + 1],
+        }
+    }
+
+    pub fn bins(&self) -> usize {
+        self.counts.len() - 1
+    }
+
+    pub fn bin_width(&self) -> f32 {
+        self.max_speed / self.bins() as f32
+    }
+
+    // Number of speeds in the window, including overflow
+    pub fn total(&self) -> u32 {
+        self.counts.iter().sum()
+    }
+
+    // Add the current speeds as one frame, dropping the oldest frame once the
+    // window is full.
+    pub fn record(&mut self, balls: &[Ball]) {
+        let bins = self.bins();
+        let mut frame = vec![0; bins + 1];
+        for ball in balls {
+            let bin = (ball.velocity.length() / self.bin_width()) as usize;
+            frame[bin.min(bins)] += 1;
+        }
+        for (total, &count) in self.counts.iter_mut().zip(&frame) {
+            *total += count;
+        }
+        self.frames.push_back(frame);
+
+        if self.frames.len() > self.window {
+            let oldest = self.frames.pop_front().unwrap();
+            for (total, &count) in self.counts.iter_mut().zip(&oldest) {
+                *total -= count;
+            }
+        }
+    }
+
+    // Probability density in `bin`, normalised over all recorded speeds
+    pub fn density(&self, bin: usize) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        self.counts[bin] as f32 / (total as f32 * self.bin_width())
+    }
+
+    // Kolmogorov-Smirnov distance: largest gap between the recorded and the
+    // theoretical cumulative distribution, evaluated at the bin edges.
+    pub fn ks_distance(&self, theory: &MaxwellBoltzmann) -> f32 {
+        let total = self.total();
+        if total == 0 {
+            return 0.0;
+        }
+        let mut below = 0;
+        let mut distance: f32 = 0.0;
+        for bin in 0..self.bins() {
+            below += self.counts[bin];
+            let edge = (bin + 1) as f32 * self.bin_width();
+            let gap = below as f32 / total as f32 - theory.cdf(edge);
+            distance = distance.max(gap.abs());
+        }
+        distance
+    }
+
+    // Pearson chi-squared of the counts against the theory, and its number of
+    // degrees of freedom. Bins expecting fewer than five counts are merged
+    // into their neighbours, the overflow included. Frames in the window are
+    // correlated, so read this as a distance rather than a p-value.
+    pub fn chi_squared(&self, theory: &MaxwellBoltzmann) -> (f32, usize) {
+        let total = self.total() as f32;
+        let mut chi2 = 0.0;
+        let mut groups: usize = 0;
+        let (mut observed, mut expected) = (0.0, 0.0);
+        for bin in 0..=self.bins() {
+            let low = bin as f32 * self.bin_width();
+            let p = if bin == self.bins() {
+                1.0 - theory.cdf(low)
+            } else {
+                theory.cdf(low + self.bin_width()) - theory.cdf(low)
+            };
+            observed += self.counts[bin] as f32;
+            expected += total * p;
+            if expected >= 5.0 {
+                chi2 += (observed - expected).powi(2) / expected;
+                groups += 1;
+                (observed, expected) = (0.0, 0.0);
+            }
+        }
+        // Leftover tail that never reached five expected counts
+        if expected > 0.0 {
+            chi2 += (observed - expected).powi(2) / expected;
+            groups += 1;
+        }
+        // The temperature is fitted to the data, costing one degree of freedom
+        // on top of the normalisation
+        (chi2, groups.saturating_sub(2))
+    }
+
+    // Bars of the recorded density with the theory curve on top, in a panel
+    // with its top-left corner at `origin`.
+    pub fn draw(&self, theory: &MaxwellBoltzmann, origin: Vec2, size: Vec2) {
+        draw_rectangle(
+            origin.x,
+            origin.y,
+            size.x,
+            size.y,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        draw_rectangle_lines(origin.x, origin.y, size.x, size.y, 1.0, GRAY);
+
+        // Leave room for the caption at the top
+        let plot_top = origin.y + 24.0;
+        let bottom = origin.y + size.y;
+        let height = bottom - plot_top;
+        let peak = (0..self.bins())
+            .map(|bin| self.density(bin))
+            .chain((0..=100).map(|k| theory.pdf(k as f32 / 100.0 * self.max_speed)))
+            .fold(f32::EPSILON, f32::max);
+        let bar_width = size.x / self.bins() as f32;
+
+        for bin in 0..self.bins() {
+            let h = self.density(bin) / peak * height;
+            let x = origin.x + bin as f32 * bar_width;
+            draw_rectangle(x + 1.0, bottom - h, bar_width - 2.0, h, SKYBLUE);
+        }
+
+        let point = |k: usize| {
+            let speed = k as f32 / 100.0 * self.max_speed;
+            vec2(
+                origin.x + k as f32 / 100.0 * size.x,
+                bottom - theory.pdf(speed) / peak * height,
+            )
+        };
+        for k in 0..100 {
+            let (p0, p1) = (point(k), point(k + 1));
+            draw_line(p0.x, p0.y, p1.x, p1.y, 2.0, ORANGE);
+        }
+
+        let (chi2, dof) = self.chi_squared(theory);
+        let caption = format!(
+            "|v|  kT={:.0}  KS={:.3}  chi2/dof={:.1}",
+            theory.kt,
+            self.ks_distance(theory),
+            chi2 / dof.max(1) as f32
+        );
+        draw_text(&caption, origin.x + 6.0, origin.y + 16.0, 16.0, WHITE);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sim::{self, Container, SweepAndPrune};
+
+    fn balls_with_speeds(speeds: impl Iterator<Item = f32>) -> Vec<Ball> {
+        speeds
+            .enumerate()
+            .map(|(k, speed)| {
+                let angle = k as f32 * 2.4;
+                let velocity = vec2(angle.cos(), angle.sin()) * speed;
+                Ball::new(Vec2::ZERO, velocity, 10.0, RED)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_maxwell_boltzmann_is_normalised() {
+        let mut balls = balls_with_speeds((0..40).map(|k| 20.0 + 5.0 * k as f32));
+        for (k, ball) in balls.iter_mut().enumerate() {
+            ball.mass = 50.0 + 30.0 * (k % 5) as f32;
+        }
+        let theory = MaxwellBoltzmann::from_balls(&balls);
+
+        let steps = 2000;
+        let dv = theory.speed_range() / steps as f32;
+        let integral: f32 = (0..steps)
+            .map(|k| theory.pdf((k as f32 + 0.5) * dv) * dv)
+            .sum();
+        assert!((integral - theory.cdf(theory.speed_range())).abs() < 1e-3);
+        assert!(theory.cdf(theory.speed_range()) > 0.999);
+    }
+
+    #[test]
+    fn test_histogram_of_maxwell_boltzmann_speeds_fits() {
+        // Quantiles of the equal-mass distribution: v = sqrt(-2 kT/m ln(1 - u))
+        let (kt, mass) = (2.0e5_f32, 100.0);
+        let count = 2000;
+        let speeds = (0..count).map(|k| {
+            let u = (k as f32 + 0.5) / count as f32;
+            (-2.0 * kt / mass * (1.0 - u).ln()).sqrt()
+        });
+        let balls = balls_with_speeds(speeds);
+        let theory = MaxwellBoltzmann::from_balls(&balls);
+        assert!((theory.kt / kt - 1.0).abs() < 0.01);
+
+        let mut histogram = SpeedHistogram::new(30, theory.speed_range(), 10);
+        histogram.record(&balls);
+        assert_eq!(histogram.total(), count);
+        assert!(histogram.ks_distance(&theory) < 0.01);
+        let (chi2, dof) = histogram.chi_squared(&theory);
+        assert!(chi2 < dof as f32, "chi2 = {chi2} for {dof} dof");
+    }
+
+    #[test]
+    fn test_equal_speeds_relax_towards_maxwell_boltzmann() {
+        let container = Container::Rectangle {
+            min: Vec2::ZERO,
+            max: vec2(400.0, 400.0),
+        };
+        // Every ball starts with the same speed in a different direction
+        let mut balls = balls_with_speeds(std::iter::repeat_n(100.0, 100));
+        for (k, ball) in balls.iter_mut().enumerate() {
+            ball.position = vec2(20.0 + 40.0 * (k % 10) as f32, 20.0 + 40.0 * (k / 10) as f32);
+            ball.radius = 6.0;
+        }
+        let theory = MaxwellBoltzmann::from_balls(&balls);
+        let mut histogram = SpeedHistogram::new(20, theory.speed_range(), 200);
+        histogram.record(&balls);
+        let initial = histogram.ks_distance(&theory);
+
+        let mut broad_phase = SweepAndPrune::new();
+        for step in 0..3000 {
+            for ball in &mut balls {
+                ball.update(0.01, &container);
+            }
+            for &(i, j) in broad_phase.update(&balls) {
+                let (b1, b2) = sim::pair_mut(&mut balls, i, j);
+                sim::resolve_collision(b1, b2);
+            }
+            if step % 10 == 0 {
+                histogram.record(&balls);
+            }
+        }
+
+        let theory = MaxwellBoltzmann::from_balls(&balls);
+        let relaxed = histogram.ks_distance(&theory);
+        assert!(initial > 0.4, "initial KS {initial}");
+        assert!(relaxed < 0.05, "relaxed KS {relaxed}");
+    }
+}